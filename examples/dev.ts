@@ -70,8 +70,6 @@ export function createTrayMenu() {
   return {menu,subMenu,toggleItem,helloItem};
 }
 
-// Global reference to prevent Garbage Collection
-let tray = null;
 let isRunning = true;
 
 /**
@@ -79,8 +77,8 @@ let isRunning = true;
  */
 function handleEvents(menu: Menu) {
   const trayEvent = pollTrayEvents();
-  if (trayEvent && trayEvent.eventType) {
-  //  console.log(trayEvent.eventType);
+  if (trayEvent && trayEvent.type === "click") {
+  //  console.log(trayEvent.button, trayEvent.buttonState);
   }
 
   const menuEvent = pollMenuEvents();
@@ -108,7 +106,7 @@ async function startApp() {
   const icon = Icon.fromRgba(generateIconData(), 32, 32);
   const {menu} = createTrayMenu();
 
-  tray = new TrayIconBuilder()
+  const tray = new TrayIconBuilder()
     .withTitle("My App")
     .withTooltip("Right click for menu")
     .withIcon(icon)
@@ -127,7 +125,7 @@ async function startApp() {
   }
 
   console.log("Shutting down...");
-  tray = null;
+  tray.destroy();
   process.exit(0);
 }
 
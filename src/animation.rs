@@ -0,0 +1,156 @@
+use crate::error::ErrorCode;
+use crate::icon::Icon;
+use crate::render::Badge;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::time::{Duration, Instant};
+
+const DEFAULT_FPS: f64 = 10.0;
+
+/// Frame delays up to this long are treated as unset, as browsers do for GIFs.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+
+#[napi(object)]
+pub struct AnimationOptions {
+    /// Frames per second. By default each frame is shown for its own delay when it has one, see
+    /// `Icon.frameDelay`, and at 10 frames per second otherwise.
+    pub fps: Option<f64>,
+    /// Start over after the last frame, `true` by default. Otherwise the last frame stays.
+    #[napi(js_name = "loop")]
+    pub repeat: Option<bool>,
+}
+
+/// Frames of a tray icon animation and how far it has played.
+pub(crate) struct Animation {
+    frames: Vec<Icon>,
+    /// `frames` with the tray's badge drawn on them, converted to platform icons up front so
+    /// that showing a frame costs no conversion.
+    prepared: Vec<tray_icon::Icon>,
+    /// How long each frame is shown.
+    durations: Vec<Duration>,
+    repeat: bool,
+    index: usize,
+    next_at: Instant,
+}
+
+impl Animation {
+    pub(crate) fn new(
+        frames: Vec<Icon>,
+        options: Option<AnimationOptions>,
+        badge: Option<&Badge>,
+    ) -> Result<Self, ErrorCode> {
+        if frames.is_empty() {
+            return Err(Error::new(
+                ErrorCode::InvalidArgument,
+                "An animation needs at least one frame",
+            ));
+        }
+        let (fps, repeat) = options.map_or((None, None), |options| (options.fps, options.repeat));
+        if let Some(fps) = fps.filter(|fps| !(fps.is_finite() && *fps > 0.0)) {
+            return Err(Error::new(
+                ErrorCode::InvalidArgument,
+                format!("fps must be a positive number, got {fps}"),
+            ));
+        }
+        let fallback = Duration::from_secs_f64(1.0 / fps.unwrap_or(DEFAULT_FPS));
+        let durations: Vec<Duration> = frames
+            .iter()
+            .map(|frame| match frame.delay {
+                Some(delay) if fps.is_none() && delay > MIN_FRAME_DELAY => delay,
+                _ => fallback,
+            })
+            .collect();
+        let prepared = prepare(&frames, badge)?;
+        Ok(Self {
+            frames,
+            prepared,
+            next_at: Instant::now() + durations[0],
+            durations,
+            repeat: repeat.unwrap_or(true),
+            index: 0,
+        })
+    }
+
+    pub(crate) fn current_frame(&self) -> tray_icon::Icon {
+        self.prepared[self.index].clone()
+    }
+
+    /// Redraws the frames with a new badge.
+    pub(crate) fn set_badge(&mut self, badge: Option<&Badge>) -> Result<(), ErrorCode> {
+        self.prepared = prepare(&self.frames, badge)?;
+        Ok(())
+    }
+
+    /// Moves to the next frame once it is due and returns it, or `None` while the current frame
+    /// should stay.
+    pub(crate) fn advance(&mut self, now: Instant) -> Option<tray_icon::Icon> {
+        if now < self.next_at || self.is_finished() {
+            return None;
+        }
+        self.index = if self.index + 1 < self.prepared.len() {
+            self.index + 1
+        } else if self.repeat {
+            0
+        } else {
+            return None;
+        };
+        let duration = self.durations[self.index];
+        self.next_at += duration;
+        if self.next_at < now {
+            // Fell behind, e.g. because the JS thread was busy: skip ahead instead of catching up.
+            self.next_at = now + duration;
+        }
+        Some(self.current_frame())
+    }
+
+    /// Whether a non-looping animation has shown its last frame.
+    pub(crate) fn is_finished(&self) -> bool {
+        !self.repeat && self.index + 1 == self.prepared.len()
+    }
+}
+
+fn prepare(frames: &[Icon], badge: Option<&Badge>) -> Result<Vec<tray_icon::Icon>, ErrorCode> {
+    frames
+        .iter()
+        .map(|frame| match badge {
+            Some(badge) => badge.draw(frame)?.tray_icon(),
+            None => frame.tray_icon(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, AnimationOptions};
+    use crate::icon::Icon;
+    use std::time::Duration;
+
+    #[test]
+    fn shows_frames_for_their_delays() {
+        let frame = Icon::from_rgba_vec(vec![0; 4], 1, 1).unwrap();
+        let frames = vec![
+            Icon {
+                delay: Some(Duration::from_millis(500)),
+                ..frame.clone()
+            },
+            Icon {
+                delay: Some(Duration::ZERO),
+                ..frame.clone()
+            },
+            frame,
+        ];
+        let animation = Animation::new(frames.clone(), None, None).unwrap();
+        let default = Duration::from_millis(100);
+        assert_eq!(
+            animation.durations,
+            [Duration::from_millis(500), default, default]
+        );
+
+        let options = AnimationOptions {
+            fps: Some(20.0),
+            repeat: None,
+        };
+        let animation = Animation::new(frames, Some(options), None).unwrap();
+        assert_eq!(animation.durations, [Duration::from_millis(50); 3]);
+    }
+}
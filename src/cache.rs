@@ -0,0 +1,285 @@
+use crate::error::ErrorCode;
+use crate::icon::Icon;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+const DEFAULT_MAX_ENTRIES: u32 = 128;
+const DEFAULT_MAX_BYTES: u32 = 32 * 1024 * 1024;
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| {
+    Mutex::new(Cache {
+        entries: HashMap::new(),
+        buffers: HashMap::new(),
+        bytes: 0,
+        max_entries: DEFAULT_MAX_ENTRIES as usize,
+        max_bytes: DEFAULT_MAX_BYTES as usize,
+        revalidate_after: None,
+        clock: 0,
+    })
+});
+
+#[napi(object)]
+pub struct IconCacheLimits {
+    /// Most icons kept, 128 by default.
+    pub max_entries: Option<u32>,
+    /// Most bytes of decoded pixels kept, 32 MiB by default.
+    pub max_bytes: Option<u32>,
+    /// Check a cached icon's file for changes again once this many milliseconds have passed
+    /// since it was last checked. By default files are only checked again after `refresh()`.
+    pub revalidate_after_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct IconCacheStats {
+    pub entries: u32,
+    /// Bytes of decoded pixels held by the cache. Icons sharing their pixels count once.
+    pub bytes: f64,
+}
+
+/// Process-wide cache of icons loaded from files.
+///
+/// An icon is decoded once and handed out again without touching its file. The file is checked
+/// for changes, judged by its modification time and size, after `refresh()` or once the
+/// `revalidateAfterMs` limit passed. The least recently used icons are dropped once a limit is hit.
+#[napi]
+pub struct IconCache {}
+
+#[napi]
+impl IconCache {
+    /// Returns the icon at `path`, decoding it only when it is not cached or its file was found
+    /// to have changed.
+    #[napi]
+    pub fn get(path: String) -> Result<Icon, ErrorCode> {
+        load(&path)
+    }
+
+    /// Loads `paths` into the cache on a worker thread.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn preload(paths: Vec<String>) -> AsyncTask<Preload> {
+        AsyncTask::new(Preload(paths))
+    }
+
+    /// Makes the next `get` of `path`, or of every cached icon without a path, check whether the
+    /// file changed.
+    #[napi]
+    pub fn refresh(path: Option<String>) {
+        let mut cache = CACHE.lock().unwrap();
+        match path {
+            Some(path) => {
+                if let Some(entry) = cache.entries.get_mut(&path) {
+                    entry.stale = true;
+                }
+            }
+            None => cache
+                .entries
+                .values_mut()
+                .for_each(|entry| entry.stale = true),
+        }
+    }
+
+    /// Drops the icon at `path` from the cache. Returns whether it was cached.
+    #[napi]
+    pub fn evict(path: String) -> bool {
+        CACHE.lock().unwrap().remove(&path)
+    }
+
+    #[napi]
+    pub fn clear() {
+        let mut cache = CACHE.lock().unwrap();
+        cache.entries.clear();
+        cache.buffers.clear();
+        cache.bytes = 0;
+    }
+
+    /// Changes the limits, dropping icons right away if the cache is now over them.
+    #[napi]
+    pub fn set_limits(limits: IconCacheLimits) {
+        let mut cache = CACHE.lock().unwrap();
+        if let Some(max_entries) = limits.max_entries {
+            cache.max_entries = max_entries as usize;
+        }
+        if let Some(max_bytes) = limits.max_bytes {
+            cache.max_bytes = max_bytes as usize;
+        }
+        if let Some(ms) = limits.revalidate_after_ms {
+            cache.revalidate_after = Some(Duration::from_millis(u64::from(ms)));
+        }
+        cache.trim();
+    }
+
+    #[napi]
+    pub fn stats() -> IconCacheStats {
+        let cache = CACHE.lock().unwrap();
+        IconCacheStats {
+            entries: cache.entries.len() as u32,
+            bytes: cache.bytes as f64,
+        }
+    }
+}
+
+/// Loads icons into the cache off the JS thread for `IconCache.preload`.
+pub struct Preload(Vec<String>);
+
+impl Task for Preload {
+    type Output = Result<(), ErrorCode>;
+    type JsValue = ();
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(self.0.iter().try_for_each(|path| load(path).map(drop)))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        output.map_err(|e| JsError::from(e).into_unknown(env).into())
+    }
+}
+
+/// What a cached icon was decoded from, to notice when its file changes.
+#[derive(Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &str) -> Result<Self, ErrorCode> {
+        let metadata = std::fs::metadata(path).map_err(|e| {
+            Error::new(
+                ErrorCode::ImageError,
+                format!("Failed to open image {path}: {e}"),
+            )
+        })?;
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+struct Entry {
+    icon: Icon,
+    stamp: FileStamp,
+    /// When `stamp` was last compared with the file.
+    checked_at: Instant,
+    /// Set by `refresh()`: the file has to be checked before the icon is handed out again.
+    stale: bool,
+    last_used: u64,
+}
+
+struct Cache {
+    entries: HashMap<String, Entry>,
+    /// Number of entries sharing each pixel buffer, keyed by its address, so that `bytes` counts
+    /// every buffer once.
+    buffers: HashMap<usize, usize>,
+    bytes: usize,
+    max_entries: usize,
+    max_bytes: usize,
+    revalidate_after: Option<Duration>,
+    /// Incremented on every use, orders entries for eviction.
+    clock: u64,
+}
+
+impl Cache {
+    /// The cached icon for `path`, unless its file is due to be checked.
+    fn lookup(&mut self, path: &str, now: Instant) -> Option<Icon> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        let expired = self
+            .revalidate_after
+            .is_some_and(|after| now >= entry.checked_at + after);
+        if entry.stale || expired {
+            return None;
+        }
+        entry.last_used = self.clock;
+        Some(entry.icon.clone())
+    }
+
+    /// The cached icon for `path` if it was decoded from a file with `stamp`.
+    fn revalidate(&mut self, path: &str, stamp: FileStamp, now: Instant) -> Option<Icon> {
+        let entry = self.entries.get_mut(path)?;
+        if entry.stamp != stamp {
+            return None;
+        }
+        entry.stale = false;
+        entry.checked_at = now;
+        entry.last_used = self.clock;
+        Some(entry.icon.clone())
+    }
+
+    fn insert(&mut self, path: String, stamp: FileStamp, icon: Icon, now: Instant) {
+        self.remove(&path);
+        self.clock += 1;
+        let users = self.buffers.entry(buffer_key(&icon)).or_default();
+        if *users == 0 {
+            self.bytes += icon.rgba.len();
+        }
+        *users += 1;
+        self.entries.insert(
+            path,
+            Entry {
+                icon,
+                stamp,
+                checked_at: now,
+                stale: false,
+                last_used: self.clock,
+            },
+        );
+        self.trim();
+    }
+
+    fn remove(&mut self, path: &str) -> bool {
+        let Some(entry) = self.entries.remove(path) else {
+            return false;
+        };
+        let key = buffer_key(&entry.icon);
+        if let Some(users) = self.buffers.get_mut(&key) {
+            *users -= 1;
+            if *users == 0 {
+                self.buffers.remove(&key);
+                self.bytes -= entry.icon.rgba.len();
+            }
+        }
+        true
+    }
+
+    /// Drops the least recently used icons until the cache is within its limits.
+    fn trim(&mut self) {
+        while self.entries.len() > self.max_entries || self.bytes > self.max_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+}
+
+/// Identifies the pixel buffer of `icon`. Stays unique while a cached entry holds the buffer.
+fn buffer_key(icon: &Icon) -> usize {
+    Arc::as_ptr(&icon.rgba) as *const u8 as usize
+}
+
+/// Returns the cached icon for `path`, decoding the file if needed.
+pub(crate) fn load(path: &str) -> Result<Icon, ErrorCode> {
+    let now = Instant::now();
+    if let Some(icon) = CACHE.lock().unwrap().lookup(path, now) {
+        return Ok(icon);
+    }
+    let stamp = FileStamp::of(path)?;
+    if let Some(icon) = CACHE.lock().unwrap().revalidate(path, stamp, now) {
+        return Ok(icon);
+    }
+    // Decode without holding the lock, so other icons can be served meanwhile.
+    let icon = Icon::open(path)?;
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(path.to_owned(), stamp, icon.clone(), now);
+    Ok(icon)
+}
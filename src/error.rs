@@ -0,0 +1,41 @@
+use napi_derive::napi;
+
+/// The `code` property of errors thrown by tray, menu and icon operations.
+#[napi(string_enum = "UPPER_SNAKE")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The tray icon was used after `destroy()`.
+    TrayDestroyed,
+    /// A tray icon with the same id already exists.
+    DuplicateId,
+    /// An argument is malformed, e.g. RGBA data that does not match the given size.
+    InvalidArgument,
+    /// An image could not be read or decoded.
+    ImageError,
+    /// An image is in a format that is not recognized or not supported.
+    UnsupportedFormat,
+    /// An image is larger than the limits set with `Icon.setLimits`.
+    LimitExceeded,
+    /// The operation is not available on this platform.
+    NotSupported,
+    /// The operation conflicts with how the module was initialized.
+    InvalidState,
+    /// The platform tray or menu implementation reported a failure.
+    PlatformError,
+}
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        match self {
+            ErrorCode::TrayDestroyed => "TRAY_DESTROYED",
+            ErrorCode::DuplicateId => "DUPLICATE_ID",
+            ErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorCode::ImageError => "IMAGE_ERROR",
+            ErrorCode::UnsupportedFormat => "UNSUPPORTED_FORMAT",
+            ErrorCode::LimitExceeded => "LIMIT_EXCEEDED",
+            ErrorCode::NotSupported => "NOT_SUPPORTED",
+            ErrorCode::InvalidState => "INVALID_STATE",
+            ErrorCode::PlatformError => "PLATFORM_ERROR",
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::ErrorCode;
+use crate::tray::live_tray_count;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const DEFAULT_INTERVAL_MS: u32 = 16;
+
+#[napi(object)]
+pub struct EventLoopOptions {
+    /// Time between two pumps of the platform event queue. Defaults to 16ms.
+    pub interval_ms: Option<u32>,
+}
+
+/// A threadsafe function that does nothing. A strong one keeps the process alive until dropped.
+type Noop<const WEAK: bool> = ThreadsafeFunction<(), (), (), Status, false, WEAK>;
+
+/// The ticker thread that is currently running, if any.
+struct Ticker {
+    stop: Arc<AtomicBool>,
+    /// Held while tray icons exist, see `update_keep_alive`.
+    keep_alive: Option<Noop<false>>,
+}
+
+static TICKER: Mutex<Option<Ticker>> = Mutex::new(None);
+
+/// Starts pumping platform events natively, replacing a JS `setInterval` around `update()`.
+///
+/// A background thread schedules `update()` on the JS thread every `intervalMs`. The loop keeps
+/// the process alive while tray icons exist, but not before the first one is built, and stops by
+/// itself once the last one is destroyed.
+/// Calling it again restarts the loop with the new options. Does nothing in threaded mode.
+#[napi]
+pub fn start_event_loop(env: Env, options: Option<EventLoopOptions>) -> Result<(), ErrorCode> {
+    stop_event_loop();
+    if crate::ui_thread::is_threaded() {
+        // The GTK thread pumps its own events.
+        return Ok(());
+    }
+
+    let interval = Duration::from_millis(u64::from(
+        options
+            .and_then(|options| options.interval_ms)
+            .unwrap_or(DEFAULT_INTERVAL_MS)
+            .max(1),
+    ));
+
+    // Skip ticks while a pump is still queued so a busy JS thread does not build up a backlog.
+    let pending = Arc::new(AtomicBool::new(false));
+    let pump_pending = pending.clone();
+    let pump: Noop<true> = env
+        .create_function_from_closure("pump", move |_| {
+            pump_pending.store(false, Ordering::Release);
+            crate::update();
+            Ok(())
+        })
+        .and_then(|pump: Function<(), ()>| {
+            pump.build_threadsafe_function()
+                .callee_handled::<false>()
+                .weak::<true>()
+                .build()
+        })
+        .map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to start event loop: {}", e.reason),
+            )
+        })?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    std::thread::Builder::new()
+        .name("tray-icon-event-loop".to_string())
+        .spawn(move || {
+            let mut had_trays = false;
+            loop {
+                std::thread::sleep(interval);
+                if !keep_running(&thread_stop, &mut had_trays, live_tray_count) {
+                    break;
+                }
+                if !pending.swap(true, Ordering::AcqRel) {
+                    pump.call((), ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            }
+        })
+        .map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to start event loop: {e}"),
+            )
+        })?;
+
+    *TICKER.lock().unwrap() = Some(Ticker {
+        stop,
+        keep_alive: None,
+    });
+    update_keep_alive(&env);
+    Ok(())
+}
+
+/// Stops the loop started by `startEventLoop`. Does nothing if it is not running.
+#[napi]
+pub fn stop_event_loop() {
+    if let Some(ticker) = TICKER.lock().unwrap().take() {
+        ticker.stop.store(true, Ordering::Release);
+    }
+}
+
+/// Decides on the ticker thread whether the loop owning `stop` goes on pumping.
+///
+/// A loop that ends because its last tray icon is gone also clears `TICKER`, under the same lock
+/// `update_keep_alive` takes, so a tray icon built afterwards never keeps the process alive for
+/// a loop that no longer runs.
+fn keep_running(
+    stop: &Arc<AtomicBool>,
+    had_trays: &mut bool,
+    tray_count: impl FnOnce() -> usize,
+) -> bool {
+    let mut ticker = TICKER.lock().unwrap();
+    if stop.load(Ordering::Acquire) {
+        return false;
+    }
+    let has_trays = tray_count() > 0;
+    if *had_trays && !has_trays {
+        if ticker
+            .as_ref()
+            .is_some_and(|ticker| Arc::ptr_eq(&ticker.stop, stop))
+        {
+            *ticker = None;
+        }
+        return false;
+    }
+    *had_trays |= has_trays;
+    true
+}
+
+/// Makes the running loop hold the process open exactly while tray icons exist. Called on the JS
+/// thread whenever a tray icon is built or destroyed.
+pub(crate) fn update_keep_alive(env: &Env) {
+    let mut ticker = TICKER.lock().unwrap();
+    let Some(ticker) = ticker.as_mut() else {
+        return;
+    };
+    if live_tray_count() == 0 {
+        ticker.keep_alive = None;
+    } else if ticker.keep_alive.is_none() {
+        // Without it the trays still work, but the process may exit under them.
+        ticker.keep_alive = env
+            .create_function_from_closure("keepAlive", |_| Ok(()))
+            .and_then(|noop: Function<(), ()>| {
+                noop.build_threadsafe_function()
+                    .callee_handled::<false>()
+                    .build()
+            })
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{keep_running, Ticker, TICKER};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn forgets_the_loop_once_the_last_tray_is_destroyed() {
+        let stop = Arc::new(AtomicBool::new(false));
+        *TICKER.lock().unwrap() = Some(Ticker {
+            stop: stop.clone(),
+            keep_alive: None,
+        });
+        let mut had_trays = false;
+        assert!(keep_running(&stop, &mut had_trays, || 0));
+        assert!(keep_running(&stop, &mut had_trays, || 1));
+        assert!(!keep_running(&stop, &mut had_trays, || 0));
+        // Building a tray icon now must not keep the process alive for the ended loop.
+        assert!(TICKER.lock().unwrap().is_none());
+
+        // An old loop that was replaced leaves the new one alone.
+        *TICKER.lock().unwrap() = Some(Ticker {
+            stop: Arc::new(AtomicBool::new(false)),
+            keep_alive: None,
+        });
+        assert!(!keep_running(&stop, &mut true, || 0));
+        assert!(TICKER.lock().unwrap().is_some());
+    }
+}
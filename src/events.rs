@@ -0,0 +1,461 @@
+use crate::error::ErrorCode;
+use crate::menu::{install_menu_event_handler, MenuEvent, MENU_EVENTS};
+use crate::tray::{install_tray_event_handler, TrayIconEvent, TRAY_EVENTS};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi_derive::napi;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tray_icon::menu::MenuEvent as RawMenuEvent;
+use tray_icon::TrayIconEvent as RawTrayIconEvent;
+
+/// JS listener registered through `onTrayEvent` / `onMenuEvent`.
+///
+/// Listeners are weak so that registering one does not keep the process alive on its own.
+pub(crate) type EventCallback<T> = ThreadsafeFunction<T, (), T, Status, false, true>;
+
+/// Receives each event with its sequence number and returns whether it accepted it.
+type Subscriber<E> = Box<dyn Fn(u64, &E) -> bool + Send + Sync>;
+
+/// Orders queued events across hubs so `pollAllEvents` can return them as they happened.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Fans out events received from tray-icon/muda to every registered subscriber.
+///
+/// tray-icon and muda only accept a single event handler, and their channel hands each event
+/// to exactly one receiver, so the hub owns that handler and delivers a copy of every event to
+/// each subscriber. Events that no subscriber accepts are queued for the `poll*` functions.
+pub(crate) struct EventHub<E> {
+    next_id: AtomicU32,
+    subscribers: Mutex<Vec<(u32, Subscriber<E>)>>,
+    queue: Mutex<VecDeque<(u64, E)>>,
+}
+
+impl<E> EventHub<E> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            next_id: AtomicU32::new(1),
+            subscribers: Mutex::new(Vec::new()),
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn dispatch(&self, event: E) {
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let subscribers = self.subscribers.lock().unwrap();
+        let mut accepted = false;
+        for (_, subscriber) in subscribers.iter() {
+            accepted |= subscriber(sequence, &event);
+        }
+        drop(subscribers);
+        if !accepted {
+            self.queue.lock().unwrap().push_back((sequence, event));
+        }
+    }
+
+    pub(crate) fn subscribe(&self, subscriber: impl Fn(&E) -> bool + Send + Sync + 'static) -> u32 {
+        self.subscribe_sequenced(move |_, event| subscriber(event))
+    }
+
+    fn subscribe_sequenced(
+        &self,
+        subscriber: impl Fn(u64, &E) -> bool + Send + Sync + 'static,
+    ) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push((id, Box::new(subscriber)));
+        id
+    }
+
+    pub(crate) fn unsubscribe(&self, id: u32) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let before = subscribers.len();
+        subscribers.retain(|(subscriber_id, _)| *subscriber_id != id);
+        subscribers.len() != before
+    }
+
+    pub(crate) fn pop(&self) -> Option<E> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pop_front()
+            .map(|(_, event)| event)
+    }
+
+    /// Removes the oldest queued event accepted by `filter`, leaving the others queued.
+    pub(crate) fn pop_matching(&self, filter: impl Fn(&E) -> bool) -> Option<E> {
+        let mut queue = self.queue.lock().unwrap();
+        let index = queue.iter().position(|(_, event)| filter(event))?;
+        queue.remove(index).map(|(_, event)| event)
+    }
+
+    fn has_queued(&self, filter: impl Fn(&E) -> bool) -> bool {
+        self.queue
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, event)| filter(event))
+    }
+
+    /// Puts an event a subscriber accepted but never handed out back into the queue, in the
+    /// order it originally happened.
+    fn requeue(&self, sequence: u64, event: E) {
+        let mut queue = self.queue.lock().unwrap();
+        let index = queue.partition_point(|(queued, _)| *queued < sequence);
+        queue.insert(index, (sequence, event));
+    }
+
+    /// Removes up to `max` queued events (all of them when `None`), oldest first.
+    pub(crate) fn drain(&self, max: Option<u32>) -> Vec<E> {
+        let mut queue = self.queue.lock().unwrap();
+        let count = max.map_or(queue.len(), |max| queue.len().min(max as usize));
+        queue.drain(..count).map(|(_, event)| event).collect()
+    }
+
+    fn front_sequence(&self) -> Option<u64> {
+        self.queue
+            .lock()
+            .unwrap()
+            .front()
+            .map(|(sequence, _)| *sequence)
+    }
+}
+
+impl<E: Clone + Send + 'static> EventHub<E> {
+    /// Subscribes a buffer that receives the first `limit` events accepted by `filter`.
+    pub(crate) fn subscription(
+        &'static self,
+        filter: impl Fn(&E) -> bool + Send + Sync + 'static,
+        limit: usize,
+    ) -> Subscription<E> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let remaining = AtomicUsize::new(limit);
+        let id = self.subscribe_sequenced(move |sequence, event: &E| {
+            filter(event)
+                && remaining
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                    .is_ok()
+                && sender.send((sequence, event.clone())).is_ok()
+        });
+        Subscription {
+            hub: self,
+            id,
+            receiver,
+        }
+    }
+
+    /// Resolves with the oldest queued event accepted by `filter`, or else with the next one to
+    /// arrive. Returns `None` on timeout.
+    pub(crate) async fn next(
+        &'static self,
+        filter: impl Fn(&E) -> bool + Send + Sync + 'static,
+        timeout: Option<Duration>,
+    ) -> Option<E> {
+        let filter = Arc::new(filter);
+        loop {
+            // Subscribe before looking at the queue so no event can slip in between.
+            let accepts = filter.clone();
+            let mut subscription = self.subscription(move |event| accepts(event), 1);
+            if !self.has_queued(&*filter) {
+                return subscription.recv(timeout).await.map(|(_, event)| event);
+            }
+            // Dropping the subscription requeues anything it caught, so the queue holds the
+            // oldest event. Another consumer may take it first, hence the loop.
+            drop(subscription);
+            if let Some(event) = self.pop_matching(&*filter) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Buffers the events a hub subscriber accepted until they are read.
+///
+/// Events still buffered when it is dropped go back to the hub's queue, so a timeout or a
+/// closed stream never loses them.
+pub(crate) struct Subscription<E: 'static> {
+    hub: &'static EventHub<E>,
+    id: u32,
+    receiver: UnboundedReceiver<(u64, E)>,
+}
+
+impl<E: 'static> Subscription<E> {
+    /// Waits for the next event, returning `None` on timeout or once unsubscribed.
+    async fn recv(&mut self, timeout: Option<Duration>) -> Option<(u64, E)> {
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.receiver.recv())
+                .await
+                .ok()
+                .flatten(),
+            None => self.receiver.recv().await,
+        }
+    }
+}
+
+impl<E: 'static> Drop for Subscription<E> {
+    fn drop(&mut self) {
+        // Unsubscribing first guarantees nothing is sent after the buffer is emptied.
+        self.hub.unsubscribe(self.id);
+        while let Ok((sequence, event)) = self.receiver.try_recv() {
+            self.hub.requeue(sequence, event);
+        }
+    }
+}
+
+#[napi(string_enum = "lowercase")]
+pub enum EventSource {
+    Tray,
+    Menu,
+}
+
+/// A queued event tagged with where it came from. Exactly one of `tray` / `menu` is set.
+#[napi(object)]
+pub struct AnyEvent {
+    pub source: EventSource,
+    pub tray: Option<TrayIconEvent>,
+    pub menu: Option<MenuEvent>,
+}
+
+/// Removes up to `max` queued tray and menu events (all of them when omitted) in the order
+/// they happened.
+#[napi]
+pub fn poll_all_events(max: Option<u32>) -> Vec<AnyEvent> {
+    install_tray_event_handler();
+    install_menu_event_handler();
+    let limit = max.map_or(usize::MAX, |max| max as usize);
+    let mut events = Vec::new();
+    while events.len() < limit {
+        let event = match (TRAY_EVENTS.front_sequence(), MENU_EVENTS.front_sequence()) {
+            (Some(tray), Some(menu)) if menu < tray => MENU_EVENTS.pop().map(AnyEvent::menu),
+            (Some(_), _) => TRAY_EVENTS.pop().map(AnyEvent::tray),
+            (None, Some(_)) => MENU_EVENTS.pop().map(AnyEvent::menu),
+            (None, None) => None,
+        };
+        match event {
+            Some(event) => events.push(event),
+            None => break,
+        }
+    }
+    events
+}
+
+impl AnyEvent {
+    fn tray(event: RawTrayIconEvent) -> Self {
+        Self {
+            source: EventSource::Tray,
+            tray: Some(event.into()),
+            menu: None,
+        }
+    }
+
+    fn menu(event: RawMenuEvent) -> Self {
+        Self {
+            source: EventSource::Menu,
+            tray: None,
+            menu: Some(event.into()),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct WaitOptions {
+    /// Stop waiting after this many milliseconds. Waits forever when omitted.
+    pub timeout_ms: Option<u32>,
+}
+
+pub(crate) fn timeout_of(options: Option<WaitOptions>) -> Option<Duration> {
+    options
+        .and_then(|options| options.timeout_ms)
+        .map(|ms| Duration::from_millis(u64::from(ms)))
+}
+
+/// Returns a Promise that resolves with the output of `future`.
+pub(crate) fn spawn<T: ToNapiValue + Send + 'static>(
+    env: &Env,
+    future: impl Future<Output = T> + Send + 'static,
+) -> Result<PromiseRaw<'_, T>, ErrorCode> {
+    env.spawn_future(async move { Ok(future.await) })
+        .map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to wait for events: {}", e.reason),
+            )
+        })
+}
+
+/// Shared state behind the `TrayEventStream` / `MenuEventStream` async iterators.
+pub(crate) struct EventStream<E: 'static> {
+    hub: &'static EventHub<E>,
+    id: u32,
+    subscription: Arc<tokio::sync::Mutex<Option<Subscription<E>>>>,
+    closed: Arc<AtomicBool>,
+    timeout: Option<Duration>,
+}
+
+impl<E: Send + 'static> EventStream<E> {
+    pub(crate) fn new(subscription: Subscription<E>, timeout: Option<Duration>) -> Self {
+        Self {
+            hub: subscription.hub,
+            id: subscription.id,
+            subscription: Arc::new(tokio::sync::Mutex::new(Some(subscription))),
+            closed: Arc::new(AtomicBool::new(false)),
+            timeout,
+        }
+    }
+
+    /// Resolves with the next event, or `None` once the stream is closed or timed out.
+    ///
+    /// A timeout closes the stream so the subscription does not outlive the consumer.
+    pub(crate) fn next<T: From<E>>(
+        &self,
+    ) -> impl Future<Output = Result<Option<T>>> + Send + 'static {
+        let subscription = self.subscription.clone();
+        let closed = self.closed.clone();
+        let timeout = self.timeout;
+        async move {
+            let mut slot = subscription.lock().await;
+            let Some(active) = slot.as_mut() else {
+                return Ok(None);
+            };
+            if !closed.load(Ordering::Acquire) {
+                if let Some((sequence, event)) = active.recv(timeout).await {
+                    if !closed.load(Ordering::Acquire) {
+                        return Ok(Some(event.into()));
+                    }
+                    // `close()` was called while this event was on its way.
+                    active.hub.requeue(sequence, event);
+                }
+            }
+            closed.store(true, Ordering::Release);
+            slot.take();
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        // Wakes a pending `next()`, which then drops the subscription itself.
+        self.hub.unsubscribe(self.id);
+        if let Ok(mut slot) = self.subscription.try_lock() {
+            slot.take();
+        }
+    }
+}
+
+/// Handle returned by `onTrayEvent` / `onMenuEvent`.
+#[napi]
+pub struct EventSubscription {
+    id: u32,
+    remove: fn(u32) -> bool,
+    active: bool,
+}
+
+#[napi]
+impl EventSubscription {
+    pub(crate) fn new(id: u32, remove: fn(u32) -> bool) -> Self {
+        Self {
+            id,
+            remove,
+            active: true,
+        }
+    }
+
+    /// Stops delivering events to the listener. Returns `false` if it was already removed.
+    #[napi]
+    pub fn unsubscribe(&mut self) -> bool {
+        if !self.active {
+            return false;
+        }
+        self.active = false;
+        (self.remove)(self.id)
+    }
+
+    #[napi(getter)]
+    pub fn active(&self) -> bool {
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventHub, EventStream};
+    use std::time::Duration;
+
+    #[test]
+    fn queues_events_no_listener_accepts() {
+        // Events carry the id of the tray icon they belong to.
+        let hub = EventHub::new();
+        hub.subscribe(|tray: &&str| *tray == "status");
+        hub.dispatch("status");
+        hub.dispatch("sync");
+        hub.dispatch("status");
+        assert_eq!(hub.pop_matching(|tray| *tray == "sync"), Some("sync"));
+        assert_eq!(hub.pop(), None);
+    }
+
+    #[test]
+    fn queues_events_no_stream_accepts() {
+        static HUB: EventHub<&str> = EventHub::new();
+        let mut status = HUB.subscription(|tray| *tray == "status", usize::MAX);
+        HUB.dispatch("status");
+        HUB.dispatch("sync");
+        assert_eq!(
+            status.receiver.try_recv().map(|(_, tray)| tray),
+            Ok("status")
+        );
+        assert_eq!(HUB.pop(), Some("sync"));
+    }
+
+    #[test]
+    fn requeues_unread_events_in_order() {
+        static HUB: EventHub<&str> = EventHub::new();
+        let subscription = HUB.subscription(|_| true, 2);
+        HUB.dispatch("first");
+        HUB.dispatch("second");
+        HUB.dispatch("third");
+        drop(subscription);
+        assert_eq!(HUB.drain(None), ["first", "second", "third"]);
+    }
+
+    #[tokio::test]
+    async fn next_returns_queued_events_first() {
+        static HUB: EventHub<&str> = EventHub::new();
+        HUB.dispatch("first");
+        HUB.dispatch("second");
+        let timeout = Some(Duration::ZERO);
+        assert_eq!(HUB.next(|_| true, timeout).await, Some("first"));
+        assert_eq!(HUB.next(|_| true, timeout).await, Some("second"));
+        assert_eq!(HUB.next(|_| true, timeout).await, None);
+    }
+
+    #[tokio::test]
+    async fn next_takes_one_event() {
+        static HUB: EventHub<&str> = EventHub::new();
+        let next = tokio::spawn(HUB.next(|_| true, None));
+        while HUB.subscribers.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        HUB.dispatch("first");
+        HUB.dispatch("second");
+        assert_eq!(next.await.unwrap(), Some("first"));
+        assert_eq!(HUB.pop(), Some("second"));
+    }
+
+    #[tokio::test]
+    async fn closed_streams_requeue_unread_events() {
+        static HUB: EventHub<&str> = EventHub::new();
+        let stream = EventStream::new(HUB.subscription(|_| true, usize::MAX), None);
+        HUB.dispatch("first");
+        HUB.dispatch("second");
+        assert_eq!(stream.next::<&str>().await.unwrap(), Some("first"));
+        stream.close();
+        assert_eq!(stream.next::<&str>().await.unwrap(), None);
+        assert_eq!(HUB.pop(), Some("second"));
+    }
+}
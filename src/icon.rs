@@ -1,44 +1,1016 @@
+use crate::cache;
+use crate::common::Rect;
+use crate::error::ErrorCode;
+use crate::render::{self, Badge, BadgeOptions, TextIconOptions};
+use base64::Engine;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::error::{LimitError, LimitErrorKind};
+use image::imageops::{self, FilterType};
+use image::{
+    AnimationDecoder, DynamicImage, Frame, Frames, ImageDecoder, ImageError, ImageFormat,
+    ImageReader, ImageResult, RgbaImage,
+};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::Duration;
+use tray_icon::menu as tray_menu;
 
+/// System fonts for `<text>` elements in SVG icons, loaded on first use.
+static SVG_FONTS: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut fonts = usvg::fontdb::Database::new();
+    fonts.load_system_fonts();
+    Arc::new(fonts)
+});
+
+/// Resampling filter used by `Icon.resize`, from fastest to sharpest.
+#[napi(string_enum = "lowercase")]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct SvgOptions {
+    /// Width in pixels. Derived from `height` and the SVG's aspect ratio when omitted.
+    pub width: Option<u32>,
+    /// Height in pixels. Derived from `width` and the SVG's aspect ratio when omitted.
+    pub height: Option<u32>,
+}
+
+#[napi(object)]
+pub struct IconLimits {
+    /// Largest width or height accepted, 4096 pixels by default.
+    pub max_dimension: Option<u32>,
+    /// Most bytes an image may take once decoded, 64 MiB by default. Decoders stop as soon as
+    /// they would allocate more.
+    pub max_bytes: Option<u32>,
+}
+
+#[derive(Clone, Copy)]
+struct Limits {
+    max_dimension: u32,
+    max_bytes: u64,
+}
+
+static LIMITS: Mutex<Limits> = Mutex::new(Limits {
+    max_dimension: 4096,
+    max_bytes: 64 * 1024 * 1024,
+});
+
+impl Limits {
+    fn current() -> Self {
+        *LIMITS.lock().unwrap()
+    }
+
+    fn decoder(self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+        limits.max_alloc = Some(self.max_bytes);
+        limits
+    }
+}
+
+#[napi(object)]
+pub struct IconLoadOptions {
+    /// Load through `IconCache`, so the file is only decoded again once it changes.
+    pub cache: Option<bool>,
+}
+
+/// Pixels of every icon alive, keyed by content hash, so equal icons share one buffer.
+///
+/// The pixels sit behind their own allocation, so an entry whose icons were all dropped only
+/// keeps a few bytes alive until it is cleaned up.
+static INTERNED: LazyLock<Mutex<HashMap<u64, Weak<Pixels>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// RGBA data shared by every icon with the same size and content.
+pub(crate) struct Pixels {
+    rgba: Box<[u8]>,
+    width: u32,
+    height: u32,
+    /// Made when the icon is first shown in a tray, so showing it again reuses it.
+    tray_icon: Mutex<Option<tray_icon::Icon>>,
+}
+
+impl std::ops::Deref for Pixels {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.rgba
+    }
+}
+
+/// An image for tray and menu icons.
+///
+/// Cloning is cheap: icons with the same pixels share a single buffer, and the platform tray
+/// icon is made from it once, when the icon is first shown.
 #[napi]
 #[derive(Clone)]
 pub struct Icon {
-    pub(crate) inner: tray_icon::Icon,
-    pub(crate) rgba: Vec<u8>,
+    pub(crate) rgba: Arc<Pixels>,
     pub(crate) width: u32,
     pub(crate) height: u32,
+    /// How long the frame is shown when the icon was read as part of an animation.
+    pub(crate) delay: Option<Duration>,
 }
 
 #[napi]
 impl Icon {
+    /// Changes the size limits applied to every icon that is loaded or created, so that
+    /// malformed or hostile image files cannot exhaust memory.
+    #[napi]
+    pub fn set_limits(limits: IconLimits) {
+        let mut current = LIMITS.lock().unwrap();
+        if let Some(max_dimension) = limits.max_dimension {
+            current.max_dimension = max_dimension;
+        }
+        if let Some(max_bytes) = limits.max_bytes {
+            current.max_bytes = u64::from(max_bytes);
+        }
+    }
+
+    /// Loads an image file. With `cache: true` the icon comes from `IconCache`.
     #[napi(factory)]
-    pub fn from_path(path: String) -> Result<Self> {
-        let img = image::open(&path)
-            .map_err(|e| Error::from_reason(format!("Failed to open image {path}: {e}")))?
-            .to_rgba8();
-        let (width, height) = img.dimensions();
-        let rgba = img.into_raw();
-        let icon = tray_icon::Icon::from_rgba(rgba.clone(), width, height)
-            .map_err(|e| Error::from_reason(format!("Failed to create icon: {e}")))?;
+    pub fn from_path(path: String, options: Option<IconLoadOptions>) -> Result<Self, ErrorCode> {
+        if options.and_then(|options| options.cache).unwrap_or(false) {
+            cache::load(&path)
+        } else {
+            Self::open(&path)
+        }
+    }
+
+    /// Like `fromPath`, but reads and decodes the file on a worker thread.
+    #[napi(ts_return_type = "Promise<Icon>")]
+    pub fn from_path_async(
+        path: String,
+        options: Option<IconLoadOptions>,
+    ) -> AsyncTask<DecodeIcon> {
+        AsyncTask::new(DecodeIcon(IconSource::Path(path, options)))
+    }
+
+    #[napi(factory)]
+    pub fn from_rgba(rgba: Buffer, width: u32, height: u32) -> Result<Self, ErrorCode> {
+        Self::from_rgba_slice(&rgba, width, height)
+    }
+
+    /// Decodes an encoded image (PNG, ICO, JPEG, ...) held in memory.
+    ///
+    /// The format is guessed from the data unless `formatHint` names it, either as a file
+    /// extension (`"png"`) or a MIME type (`"image/png"`).
+    #[napi(factory)]
+    pub fn from_buffer(bytes: Buffer, format_hint: Option<String>) -> Result<Self, ErrorCode> {
+        Self::decode(&bytes, format_hint.as_deref())
+    }
+
+    /// Like `fromBuffer`, but decodes on a worker thread.
+    #[napi(ts_return_type = "Promise<Icon>")]
+    pub fn from_buffer_async(bytes: Buffer, format_hint: Option<String>) -> AsyncTask<DecodeIcon> {
+        AsyncTask::new(DecodeIcon(IconSource::Bytes(bytes.to_vec(), format_hint)))
+    }
+
+    /// Rasterizes an SVG, given as a file path or its contents, at the requested size.
+    ///
+    /// Without a size the SVG's own size is used. The drawing is scaled to fit and centered when
+    /// the requested size has a different aspect ratio.
+    #[napi(factory)]
+    pub fn from_svg(
+        source: Either<String, Buffer>,
+        options: Option<SvgOptions>,
+    ) -> Result<Self, ErrorCode> {
+        let mut opt = usvg::Options {
+            fontdb: SVG_FONTS.clone(),
+            ..Default::default()
+        };
+        let data = match &source {
+            Either::A(path) => {
+                opt.resources_dir = Path::new(path).parent().map(Path::to_path_buf);
+                std::fs::read(path).map_err(|e| {
+                    Error::new(
+                        ErrorCode::ImageError,
+                        format!("Failed to read SVG {path}: {e}"),
+                    )
+                })?
+            }
+            Either::B(bytes) => bytes.to_vec(),
+        };
+        let tree = usvg::Tree::from_data(&data, &opt)
+            .map_err(|e| Error::new(ErrorCode::ImageError, format!("Failed to parse SVG: {e}")))?;
+
+        let size = tree.size();
+        let aspect = size.width() / size.height();
+        let (width, height) = match options.map(|options| (options.width, options.height)) {
+            Some((Some(width), Some(height))) => (width, height),
+            Some((Some(width), None)) => (width, (width as f32 / aspect).round() as u32),
+            Some((None, Some(height))) => ((height as f32 * aspect).round() as u32, height),
+            _ => (size.width().ceil() as u32, size.height().ceil() as u32),
+        };
+        check_size(width, height)?;
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidArgument,
+                format!("Invalid SVG icon size {width}x{height}"),
+            )
+        })?;
+        let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+        let transform = tiny_skia::Transform::from_translate(
+            (width as f32 - size.width() * scale) / 2.0,
+            (height as f32 - size.height() * scale) / 2.0,
+        )
+        .pre_scale(scale, scale);
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+        let rgba = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let pixel = pixel.demultiply();
+                [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+            })
+            .collect();
+        Self::from_rgba_vec(rgba, width, height)
+    }
+
+    /// Renders short text, e.g. `"42°"` or `"3"`, as an icon.
+    ///
+    /// The text is scaled to fit and centered. Glyphs are drawn from their outlines in a single
+    /// color, so color emoji are not supported.
+    #[napi(factory)]
+    pub fn from_text(text: String, options: Option<TextIconOptions>) -> Result<Self, ErrorCode> {
+        render::text_icon(
+            &text,
+            options.unwrap_or(TextIconOptions {
+                size: None,
+                font: None,
+                color: None,
+                background: None,
+            }),
+        )
+    }
+
+    /// Loads every frame of an animated GIF or APNG, given as a path or its contents, for
+    /// `tray.playAnimation()`. Still images give a single frame.
+    ///
+    /// Each frame keeps its delay from the file, see `frameDelay`.
+    #[napi]
+    pub fn frames_from_gif(source: Either<String, Buffer>) -> Result<Vec<Icon>, ErrorCode> {
+        let data = match &source {
+            Either::A(path) => std::fs::read(path).map_err(|e| {
+                Error::new(
+                    ErrorCode::ImageError,
+                    format!("Failed to read image {path}: {e}"),
+                )
+            })?,
+            Either::B(bytes) => bytes.to_vec(),
+        };
+        let frames = match Self::decode_frames(&data) {
+            Some(frames) => frames.map_err(|e| image_error(e, "Failed to decode animation"))?,
+            None => return Ok(vec![Self::decode(&data, None)?]),
+        };
+        frames
+            .into_iter()
+            .map(|frame| {
+                let delay = Duration::from(frame.delay());
+                let buffer = frame.into_buffer();
+                let (width, height) = buffer.dimensions();
+                let icon = Self::from_rgba_vec(buffer.into_raw(), width, height)?;
+                Ok(Self {
+                    delay: Some(delay),
+                    ..icon
+                })
+            })
+            .collect()
+    }
+
+    /// Decodes a base64 encoded image, or a `data:image/...;base64,` URL.
+    #[napi(factory)]
+    pub fn from_base64(data: String) -> Result<Self, ErrorCode> {
+        let (mime, payload) = match data.strip_prefix("data:") {
+            Some(url) => {
+                let (meta, payload) = url
+                    .split_once(',')
+                    .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, "Malformed data URL"))?;
+                let mime = meta.strip_suffix(";base64").ok_or_else(|| {
+                    Error::new(
+                        ErrorCode::InvalidArgument,
+                        "Only base64 encoded data URLs are supported",
+                    )
+                })?;
+                (Some(mime).filter(|mime| !mime.is_empty()), payload)
+            }
+            None => (None, data.as_str()),
+        };
+        let payload: String = payload
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|e| {
+                Error::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid base64 data: {e}"),
+                )
+            })?;
+        Self::decode(&bytes, mime)
+    }
+
+    #[napi(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[napi(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// How long this frame is shown by `playAnimation()` in milliseconds, for frames loaded with
+    /// `framesFromGif`. `null` for other icons.
+    #[napi(getter)]
+    pub fn frame_delay(&self) -> Option<f64> {
+        self.delay.map(|delay| delay.as_secs_f64() * 1000.0)
+    }
+
+    /// The raw pixels, 4 bytes (RGBA) per pixel, row by row from the top-left corner.
+    #[napi]
+    pub fn to_rgba(&self) -> Buffer {
+        self.rgba.to_vec().into()
+    }
+
+    /// The icon encoded as PNG.
+    #[napi]
+    pub fn to_png(&self) -> Result<Buffer, ErrorCode> {
+        let mut png = Cursor::new(Vec::new());
+        self.to_image()
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| Error::new(ErrorCode::ImageError, format!("Failed to encode PNG: {e}")))?;
+        Ok(png.into_inner().into())
+    }
+
+    /// Writes the icon to `path`, in `format` (an extension such as `"png"` or `"ico"`) or the
+    /// format matching the path's extension.
+    #[napi]
+    pub fn save(&self, path: String, format: Option<String>) -> Result<(), ErrorCode> {
+        let format = match &format {
+            Some(format) => ImageFormat::from_extension(format.trim_start_matches('.')),
+            None => ImageFormat::from_path(&path).ok(),
+        }
+        .ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidArgument,
+                format!("Cannot tell which image format to save {path} as"),
+            )
+        })?;
+        let image = DynamicImage::ImageRgba8(self.to_image());
+        let image = if format == ImageFormat::Jpeg {
+            // JPEG has no alpha channel.
+            DynamicImage::ImageRgb8(image.to_rgb8())
+        } else {
+            image
+        };
+        image.save_with_format(&path, format).map_err(|e| {
+            Error::new(
+                ErrorCode::ImageError,
+                format!("Failed to save icon to {path}: {e}"),
+            )
+        })
+    }
+
+    /// A copy with a badge, e.g. an unread count, drawn in one of its corners.
+    #[napi]
+    pub fn with_badge(&self, options: BadgeOptions) -> Result<Icon, ErrorCode> {
+        match Badge::from_options(options)? {
+            Some(badge) => badge.draw(self),
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// A copy scaled to `width` x `height` pixels, with the `lanczos3` filter by default.
+    #[napi]
+    pub fn resize(
+        &self,
+        width: u32,
+        height: u32,
+        filter: Option<ResizeFilter>,
+    ) -> Result<Icon, ErrorCode> {
+        check_size(width, height)?;
+        let filter = filter.map_or(FilterType::Lanczos3, FilterType::from);
+        let resized = imageops::resize(&self.to_image(), width, height, filter);
+        Self::from_rgba_vec(resized.into_raw(), width, height)
+    }
+
+    /// A grayscale copy, keeping transparency. Handy for disabled states.
+    #[napi]
+    pub fn grayscale(&self) -> Result<Icon, ErrorCode> {
+        self.map_pixels(|[r, g, b, a]| {
+            let luma =
+                (0.299 * f32::from(r) + 0.587 * f32::from(g) + 0.114 * f32::from(b)).round() as u8;
+            [luma, luma, luma, a]
+        })
+    }
+
+    /// A copy with every pixel's color mixed toward `color` (`#rgb`, `#rrggbb` or with an alpha
+    /// digit pair), keeping transparency.
+    ///
+    /// `strength` goes from 0 (unchanged) to 1 (the default), which recolors the whole shape.
+    #[napi]
+    pub fn tint(&self, color: String, strength: Option<f64>) -> Result<Icon, ErrorCode> {
+        let [tr, tg, tb, ta] = parse_color(&color)?;
+        let strength = unit_interval("strength", strength.unwrap_or(1.0))? * f64::from(ta) / 255.0;
+        let mix = |from: u8, to: u8| {
+            (f64::from(from) + (f64::from(to) - f64::from(from)) * strength).round() as u8
+        };
+        self.map_pixels(|[r, g, b, a]| [mix(r, tr), mix(g, tg), mix(b, tb), a])
+    }
+
+    /// A copy with inverted colors, keeping transparency.
+    #[napi]
+    pub fn invert(&self) -> Result<Icon, ErrorCode> {
+        self.map_pixels(|[r, g, b, a]| [255 - r, 255 - g, 255 - b, a])
+    }
+
+    /// A copy with its transparency multiplied by `alpha`, from 0 (invisible) to 1 (unchanged).
+    #[napi]
+    pub fn opacity(&self, alpha: f64) -> Result<Icon, ErrorCode> {
+        let alpha = unit_interval("alpha", alpha)?;
+        self.map_pixels(|[r, g, b, a]| [r, g, b, (f64::from(a) * alpha).round() as u8])
+    }
+
+    /// A copy with a transparent border of `padding` pixels on every side.
+    #[napi]
+    pub fn pad(&self, padding: u32) -> Result<Icon, ErrorCode> {
+        let grow = |size: u32| {
+            padding
+                .checked_mul(2)
+                .and_then(|border| size.checked_add(border))
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorCode::InvalidArgument,
+                        format!("Padding {padding} is too large"),
+                    )
+                })
+        };
+        let (width, height) = (grow(self.width)?, grow(self.height)?);
+        check_size(width, height)?;
+        let mut padded = RgbaImage::new(width, height);
+        imageops::replace(
+            &mut padded,
+            &self.to_image(),
+            i64::from(padding),
+            i64::from(padding),
+        );
+        Self::from_rgba_vec(padded.into_raw(), width, height)
+    }
+
+    /// A copy of the `rect` area, in pixels from the top-left corner.
+    #[napi]
+    pub fn crop(&self, rect: Rect) -> Result<Icon, ErrorCode> {
+        let in_bounds = rect.x >= 0.0
+            && rect.y >= 0.0
+            && rect.width >= 1.0
+            && rect.height >= 1.0
+            && rect.x + rect.width <= f64::from(self.width)
+            && rect.y + rect.height <= f64::from(self.height);
+        if !in_bounds {
+            return Err(Error::new(
+                ErrorCode::InvalidArgument,
+                format!(
+                    "Crop area {}x{} at {},{} is outside the {}x{} icon",
+                    rect.width, rect.height, rect.x, rect.y, self.width, self.height
+                ),
+            ));
+        }
+        let cropped = imageops::crop_imm(
+            &self.to_image(),
+            rect.x as u32,
+            rect.y as u32,
+            rect.width as u32,
+            rect.height as u32,
+        )
+        .to_image();
+        let (width, height) = cropped.dimensions();
+        Self::from_rgba_vec(cropped.into_raw(), width, height)
+    }
+}
+
+impl Icon {
+    pub(crate) fn from_rgba_vec(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, ErrorCode> {
+        Self::from_rgba_slice(&rgba, width, height)
+    }
+
+    /// Wraps pixels, sharing the buffer of an equal icon that is still alive.
+    fn from_rgba_slice(rgba: &[u8], width: u32, height: u32) -> Result<Self, ErrorCode> {
+        check_size(width, height)?;
+        let expected = u64::from(width) * u64::from(height) * 4;
+        if rgba.len() as u64 != expected {
+            return Err(Error::new(
+                ErrorCode::InvalidArgument,
+                format!(
+                    "RGBA data has {} bytes, a {width}x{height} icon needs {expected}",
+                    rgba.len()
+                ),
+            ));
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (width, height, rgba).hash(&mut hasher);
+        let hash = hasher.finish();
+        let mut interned = INTERNED.lock().unwrap();
+        let rgba = match interned.get(&hash).and_then(Weak::upgrade) {
+            Some(shared)
+                if (shared.width, shared.height) == (width, height) && *shared.rgba == *rgba =>
+            {
+                shared
+            }
+            _ => {
+                if interned.len() == interned.capacity() {
+                    // Forget dropped buffers before the table grows.
+                    interned.retain(|_, rgba| rgba.strong_count() > 0);
+                }
+                let rgba = Arc::new(Pixels {
+                    rgba: rgba.into(),
+                    width,
+                    height,
+                    tray_icon: Mutex::new(None),
+                });
+                interned.insert(hash, Arc::downgrade(&rgba));
+                rgba
+            }
+        };
         Ok(Self {
-            inner: icon,
             rgba,
             width,
             height,
+            delay: None,
         })
     }
 
-    #[napi(factory)]
-    pub fn from_rgba(rgba: Buffer, width: u32, height: u32) -> Result<Self> {
-        let rgba_vec = rgba.to_vec();
-        let icon = tray_icon::Icon::from_rgba(rgba_vec.clone(), width, height)
-            .map_err(|e| Error::from_reason(format!("Failed to create icon from RGBA: {e}")))?;
-        Ok(Self {
-            inner: icon,
-            rgba: rgba_vec,
-            width,
-            height,
+    /// The platform icon to show in a tray, made on first use and shared with equal icons.
+    pub(crate) fn tray_icon(&self) -> Result<tray_icon::Icon, ErrorCode> {
+        let mut cached = self.rgba.tray_icon.lock().unwrap();
+        if let Some(icon) = &*cached {
+            return Ok(icon.clone());
+        }
+        let icon = tray_icon::Icon::from_rgba(self.rgba.to_vec(), self.width, self.height)
+            .map_err(|e| {
+                Error::new(
+                    ErrorCode::PlatformError,
+                    format!("Failed to create tray icon: {e}"),
+                )
+            })?;
+        *cached = Some(icon.clone());
+        Ok(icon)
+    }
+
+    /// The platform icon to show in a menu item.
+    pub(crate) fn menu_icon(&self) -> Result<tray_menu::Icon, ErrorCode> {
+        tray_menu::Icon::from_rgba(self.rgba.to_vec(), self.width, self.height).map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to create menu icon: {e}"),
+            )
         })
     }
+
+    pub(crate) fn open(path: &str) -> Result<Self, ErrorCode> {
+        let mut reader = ImageReader::open(path).map_err(|e| {
+            Error::new(
+                ErrorCode::ImageError,
+                format!("Failed to open image {path}: {e}"),
+            )
+        })?;
+        reader.limits(Limits::current().decoder());
+        let img = reader
+            .decode()
+            .map_err(|e| image_error(e, &format!("Failed to open image {path}")))?;
+        Self::from_image(img)
+    }
+
+    pub(crate) fn to_image(&self) -> RgbaImage {
+        RgbaImage::from_raw(self.width, self.height, self.rgba.to_vec())
+            .expect("icon RGBA data matches its size")
+    }
+
+    fn map_pixels(&self, f: impl Fn([u8; 4]) -> [u8; 4]) -> Result<Icon, ErrorCode> {
+        let rgba = self
+            .rgba
+            .chunks_exact(4)
+            .flat_map(|pixel| f([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect();
+        Self::from_rgba_vec(rgba, self.width, self.height)
+    }
+
+    fn edge(&self) -> u32 {
+        self.width.max(self.height)
+    }
+
+    pub(crate) fn from_image(img: DynamicImage) -> Result<Self, ErrorCode> {
+        let img = img.to_rgba8();
+        let (width, height) = img.dimensions();
+        Self::from_rgba_vec(img.into_raw(), width, height)
+    }
+
+    /// Decodes the frames of an animated GIF or APNG, or returns `None` for other images.
+    fn decode_frames(data: &[u8]) -> Option<ImageResult<Vec<Frame>>> {
+        let limits = Limits::current();
+        match image::guess_format(data).ok()? {
+            ImageFormat::Gif => Some(GifDecoder::new(Cursor::new(data)).and_then(|mut decoder| {
+                decoder.set_limits(limits.decoder())?;
+                collect_frames(decoder.into_frames(), limits)
+            })),
+            ImageFormat::Png => {
+                let decoder = match PngDecoder::with_limits(Cursor::new(data), limits.decoder()) {
+                    Ok(decoder) => decoder,
+                    Err(e) => return Some(Err(e)),
+                };
+                match decoder.is_apng() {
+                    Ok(true) => Some(
+                        decoder
+                            .apng()
+                            .and_then(|decoder| collect_frames(decoder.into_frames(), limits)),
+                    ),
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn decode(bytes: &[u8], format_hint: Option<&str>) -> Result<Self, ErrorCode> {
+        let mut reader = ImageReader::new(Cursor::new(bytes));
+        match format_hint {
+            Some(hint) => {
+                let format = ImageFormat::from_extension(hint.trim_start_matches('.'))
+                    .or_else(|| ImageFormat::from_mime_type(hint))
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorCode::UnsupportedFormat,
+                            format!("Unknown image format {hint}"),
+                        )
+                    })?;
+                reader.set_format(format);
+            }
+            None => {
+                reader = reader
+                    .with_guessed_format()
+                    .expect("reading from memory does not fail");
+            }
+        }
+        reader.limits(Limits::current().decoder());
+        let img = reader
+            .decode()
+            .map_err(|e| image_error(e, "Failed to decode image"))?;
+        Self::from_image(img)
+    }
+}
+
+enum IconSource {
+    Path(String, Option<IconLoadOptions>),
+    Bytes(Vec<u8>, Option<String>),
+}
+
+/// Decodes an icon off the JS thread for `Icon.fromPathAsync` / `Icon.fromBufferAsync`.
+pub struct DecodeIcon(IconSource);
+
+impl Task for DecodeIcon {
+    // Decoding errors are carried through `resolve` so the rejection keeps its `code`.
+    type Output = Result<Icon, ErrorCode>;
+    type JsValue = Icon;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(match &mut self.0 {
+            IconSource::Path(path, options) => Icon::from_path(path.clone(), options.take()),
+            IconSource::Bytes(bytes, format_hint) => Icon::decode(bytes, format_hint.as_deref()),
+        })
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        output.map_err(|e| JsError::from(e).into_unknown(env).into())
+    }
+}
+
+/// Collects the frames of an animation, failing once they take more than the byte limit
+/// altogether.
+fn collect_frames(frames: Frames, limits: Limits) -> ImageResult<Vec<Frame>> {
+    let mut total = 0;
+    frames
+        .map(|frame| {
+            let frame = frame?;
+            total += frame.buffer().as_raw().len() as u64;
+            if total > limits.max_bytes {
+                return Err(ImageError::Limits(LimitError::from_kind(
+                    LimitErrorKind::InsufficientMemory,
+                )));
+            }
+            Ok(frame)
+        })
+        .collect()
+}
+
+fn image_error(e: ImageError, context: &str) -> Error<ErrorCode> {
+    let code = match e {
+        ImageError::Unsupported(_) => ErrorCode::UnsupportedFormat,
+        ImageError::Limits(_) => ErrorCode::LimitExceeded,
+        _ => ErrorCode::ImageError,
+    };
+    Error::new(code, format!("{context}: {e}"))
+}
+
+/// Checks an icon size before pixels are allocated for it: both sides must be positive and
+/// within the limits set with `Icon.setLimits`.
+pub(crate) fn check_size(width: u32, height: u32) -> Result<(), ErrorCode> {
+    if width == 0 || height == 0 {
+        return Err(Error::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid icon size {width}x{height}"),
+        ));
+    }
+    let limits = Limits::current();
+    if width.max(height) > limits.max_dimension {
+        return Err(Error::new(
+            ErrorCode::LimitExceeded,
+            format!(
+                "Icon size {width}x{height} exceeds the limit of {} pixels per side",
+                limits.max_dimension
+            ),
+        ));
+    }
+    let bytes = u64::from(width) * u64::from(height) * 4;
+    if bytes > limits.max_bytes {
+        return Err(Error::new(
+            ErrorCode::LimitExceeded,
+            format!(
+                "A {width}x{height} icon takes {bytes} bytes, more than the limit of {}",
+                limits.max_bytes
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` color into RGBA.
+pub(crate) fn parse_color(color: &str) -> Result<[u8; 4], ErrorCode> {
+    let invalid = || Error::new(ErrorCode::InvalidArgument, format!("Invalid color {color}"));
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let digits: Vec<u8> = match hex.len() {
+        3 | 4 => hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8 * 17))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?,
+        6 | 8 => (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
+    Ok([
+        digits[0],
+        digits[1],
+        digits[2],
+        digits.get(3).copied().unwrap_or(255),
+    ])
+}
+
+pub(crate) fn unit_interval(name: &str, value: f64) -> Result<f64, ErrorCode> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(Error::new(
+            ErrorCode::InvalidArgument,
+            format!("{name} must be between 0 and 1, got {value}"),
+        ))
+    }
+}
+
+/// Where an icon is shown, which decides the pixel size an `IconSet` is resolved for.
+#[derive(Clone, Copy)]
+pub(crate) enum IconTarget {
+    Tray,
+    Menu,
+}
+
+impl IconTarget {
+    /// Edge length in physical pixels the platform draws this kind of icon at.
+    fn pixel_size(self) -> u32 {
+        #[cfg(target_os = "windows")]
+        {
+            use windows_sys::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSMICON};
+            // Tray and menu icons both use the small icon size, already scaled to the system DPI.
+            let _ = self;
+            let size = unsafe { GetSystemMetrics(SM_CXSMICON) };
+            if size > 0 {
+                return size as u32;
+            }
+            16
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            // Points, at the 2x backing scale of Retina displays; a sharper image scales down
+            // cleanly on 1x displays.
+            match self {
+                IconTarget::Tray => 18 * 2,
+                IconTarget::Menu => 16 * 2,
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            use gtk::gdk::prelude::MonitorExt;
+
+            let scale = crate::ui_thread::run(|| {
+                if !gtk::is_initialized() {
+                    return 1;
+                }
+                gtk::gdk::Display::default()
+                    .and_then(|display| display.primary_monitor().or_else(|| display.monitor(0)))
+                    .map_or(1, |monitor| monitor.scale_factor().max(1) as u32)
+            })
+            .unwrap_or(1);
+            match self {
+                IconTarget::Tray => 22 * scale,
+                IconTarget::Menu => 16 * scale,
+            }
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        {
+            let _ = self;
+            32
+        }
+    }
+}
+
+/// The same icon at several resolutions. Trays and menus pick the size that best fits the
+/// platform's icon size and display scale.
+#[napi]
+#[derive(Clone, Default)]
+pub struct IconSet {
+    icons: Vec<Icon>,
+}
+
+#[napi]
+impl IconSet {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every image stored in a `.ico` file, given as a path or its contents.
+    #[napi(factory)]
+    pub fn from_ico(source: Either<String, Buffer>) -> Result<Self, ErrorCode> {
+        let data = match &source {
+            Either::A(path) => std::fs::read(path).map_err(|e| {
+                Error::new(
+                    ErrorCode::ImageError,
+                    format!("Failed to read icon file {path}: {e}"),
+                )
+            })?,
+            Either::B(bytes) => bytes.to_vec(),
+        };
+        let dir = ico::IconDir::read(Cursor::new(data)).map_err(|e| {
+            Error::new(
+                ErrorCode::ImageError,
+                format!("Failed to read ICO file: {e}"),
+            )
+        })?;
+        let mut set = Self::new();
+        for entry in dir.entries() {
+            check_size(entry.width(), entry.height())?;
+            let image = entry.decode().map_err(|e| {
+                Error::new(
+                    ErrorCode::ImageError,
+                    format!("Failed to decode ICO entry: {e}"),
+                )
+            })?;
+            set.icons.push(Icon::from_rgba_slice(
+                image.rgba_data(),
+                image.width(),
+                image.height(),
+            )?);
+        }
+        Ok(set)
+    }
+
+    /// Adds `icon` as one of the resolutions. An icon of the same size is replaced.
+    #[napi]
+    pub fn add(&mut self, icon: &Icon) {
+        self.icons
+            .retain(|other| (other.width, other.height) != (icon.width, icon.height));
+        self.icons.push(icon.clone());
+    }
+
+    /// Edge lengths of the icons in the set, smallest first.
+    #[napi]
+    pub fn sizes(&self) -> Vec<u32> {
+        let mut sizes: Vec<u32> = self.icons.iter().map(Icon::edge).collect();
+        sizes.sort_unstable();
+        sizes
+    }
+
+    /// The smallest icon at least `size` pixels wide and high, or the largest one if none is.
+    #[napi]
+    pub fn select(&self, size: u32) -> Option<Icon> {
+        self.icons
+            .iter()
+            .filter(|icon| icon.edge() >= size)
+            .min_by_key(|icon| icon.edge())
+            .or_else(|| self.icons.iter().max_by_key(|icon| icon.edge()))
+            .cloned()
+    }
+}
+
+impl IconSet {
+    pub(crate) fn select_for(&self, target: IconTarget) -> Result<Icon, ErrorCode> {
+        self.select(target.pixel_size())
+            .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, "Icon set is empty"))
+    }
+}
+
+/// Resolves an `Icon | IconSet` argument to the icon to show for `target`.
+pub(crate) fn resolve_icon(
+    icon: Either<&Icon, &IconSet>,
+    target: IconTarget,
+) -> Result<Icon, ErrorCode> {
+    match icon {
+        Either::A(icon) => Ok(icon.clone()),
+        Either::B(set) => set.select_for(target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Icon, IconSet, IconTarget};
+    use std::sync::Arc;
+
+    fn square(size: u32) -> Icon {
+        Icon::from_rgba_vec(vec![0; (size * size * 4) as usize], size, size).unwrap()
+    }
+
+    #[test]
+    fn shares_pixels_of_equal_icons_only() {
+        let icon = square(2);
+        assert!(Arc::ptr_eq(&icon.rgba, &square(2).rgba));
+        // Same bytes, different shape.
+        let wide = Icon::from_rgba_vec(vec![0; 16], 4, 1).unwrap();
+        assert!(!Arc::ptr_eq(&icon.rgba, &wide.rgba));
+    }
+
+    #[test]
+    fn makes_the_tray_icon_once() {
+        let icon = Icon::from_rgba_vec(vec![7; 36], 3, 3).unwrap();
+        assert!(icon.rgba.tray_icon.lock().unwrap().is_none());
+        icon.tray_icon().unwrap();
+        let copy = Icon::from_rgba_vec(vec![7; 36], 3, 3).unwrap();
+        assert!(copy.rgba.tray_icon.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn resolves_sets_for_the_platform_size() {
+        let mut set = IconSet::new();
+        for size in [16, 24, 32, 48, 64, 256] {
+            set.add(&square(size));
+        }
+        for target in [IconTarget::Tray, IconTarget::Menu] {
+            let wanted = target.pixel_size();
+            assert!(wanted > 0);
+            let icon = set.select_for(target).unwrap();
+            // The smallest icon that is at least as large as the platform draws it.
+            assert!(icon.width >= wanted);
+            assert!(set
+                .sizes()
+                .iter()
+                .all(|&size| size < wanted || size >= icon.width));
+        }
+        assert!(IconSet::new().select_for(IconTarget::Tray).is_err());
+    }
 }
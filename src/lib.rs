@@ -1,29 +1,78 @@
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+pub mod animation;
+pub mod cache;
 pub mod common;
+pub mod error;
+pub mod event_loop;
+pub mod events;
 pub mod icon;
 pub mod menu;
+pub mod render;
 pub mod tray;
+pub(crate) mod ui_thread;
 
+pub use animation::*;
+pub use cache::*;
 pub use common::*;
+pub use error::*;
+pub use event_loop::*;
+pub use events::*;
 pub use icon::*;
 pub use menu::*;
+pub use render::*;
 pub use tray::*;
 
+#[napi(object)]
+pub struct InitializeOptions {
+    /// Run GTK on a dedicated native thread instead of the JS thread (Linux only).
+    ///
+    /// Every tray and menu operation is then marshalled to that thread, and `update()` is no
+    /// longer needed. The regular methods block the JS thread until they have been applied
+    /// there; their `*Async` variants return a Promise instead.
+    pub threaded: Option<bool>,
+}
+
 #[napi]
-pub fn initialize() -> Result<()> {
+pub fn initialize(options: Option<InitializeOptions>) -> Result<(), ErrorCode> {
+    tray::install_tray_event_handler();
+    menu::install_menu_event_handler();
+
+    let threaded = options
+        .and_then(|options| options.threaded)
+        .unwrap_or(false);
+
     #[cfg(target_os = "linux")]
     {
+        if threaded {
+            return ui_thread::spawn();
+        }
         if gtk::init().is_err() {
-            return Err(Error::from_reason("Failed to initialize GTK"));
+            return Err(Error::new(
+                ErrorCode::PlatformError,
+                "Failed to initialize GTK",
+            ));
         }
     }
+
+    #[cfg(not(target_os = "linux"))]
+    if threaded {
+        return Err(Error::new(
+            ErrorCode::NotSupported,
+            "Threaded mode is only supported on Linux",
+        ));
+    }
+
     Ok(())
 }
 
 #[napi]
 pub fn update() {
+    if ui_thread::is_threaded() {
+        return;
+    }
+
     #[cfg(target_os = "linux")]
     {
         while gtk::events_pending() {
@@ -52,4 +101,6 @@ pub fn update() {
         // No manual event pumping is required as the system handles it automatically.
         // This function is a no-op on macOS but kept for cross-platform API consistency.
     }
+
+    tray::tick();
 }
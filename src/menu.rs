@@ -1,64 +1,115 @@
-use crate::icon::Icon;
+use crate::error::ErrorCode;
+use crate::events::{self, EventCallback, EventHub, EventStream, EventSubscription, WaitOptions};
+use crate::icon::{resolve_icon, Icon, IconSet, IconTarget};
+use crate::ui_thread::{self, UiBound, UiTask};
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
 use napi_derive::napi;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::future::Future;
+use std::sync::{Arc, Mutex, Once};
 use tray_icon::menu as tray_menu;
 
-pub enum AnyMenuItem {
-    Standard(tray_menu::MenuItem),
-    Check(tray_menu::CheckMenuItem),
-    Icon(tray_menu::IconMenuItem),
-    Submenu(tray_menu::Submenu),
+pub(crate) enum AnyMenuItem {
+    Standard(UiBound<tray_menu::MenuItem>),
+    Check(UiBound<tray_menu::CheckMenuItem>),
+    Icon(UiBound<tray_menu::IconMenuItem>),
+    Submenu(UiBound<tray_menu::Submenu>),
 }
 
-unsafe impl Send for AnyMenuItem {}
-unsafe impl Sync for AnyMenuItem {}
+/// Maps a muda failure to a `PlatformError`, prefixed with `context`.
+fn platform_err(context: &'static str) -> impl FnOnce(tray_menu::Error) -> Error<ErrorCode> {
+    move |e| Error::new(ErrorCode::PlatformError, format!("{context}: {e}"))
+}
+
+fn unknown_item(id: &str) -> Error<ErrorCode> {
+    Error::new(
+        ErrorCode::InvalidArgument,
+        format!("No menu item with id {id}"),
+    )
+}
+
+fn not_a_check_item(id: &str) -> Error<ErrorCode> {
+    Error::new(
+        ErrorCode::InvalidArgument,
+        format!("Menu item {id} is not a check item"),
+    )
+}
 
 #[napi]
+#[derive(Clone)]
 pub struct Menu {
-    pub(crate) inner: tray_menu::Menu,
+    pub(crate) inner: UiBound<tray_menu::Menu>,
     pub(crate) registry: Arc<Mutex<HashMap<String, AnyMenuItem>>>,
 }
 
 #[napi]
 impl Menu {
     #[napi(constructor)]
-    pub fn new() -> Self {
-        Self {
-            inner: tray_menu::Menu::new(),
+    pub fn new() -> Result<Self, ErrorCode> {
+        install_menu_event_handler();
+        Ok(Self {
+            inner: UiBound::new(tray_menu::Menu::new)?,
             registry: Arc::new(Mutex::new(HashMap::new())),
-        }
+        })
     }
     fn register(&self, id: String, item: AnyMenuItem) {
         let mut reg = self.registry.lock().unwrap();
         reg.insert(id, item);
     }
     #[napi]
-    pub fn append_check_menu_item(&self, item: &CheckMenuItem, id: String) -> Result<()> {
+    pub fn append_check_menu_item(
+        &self,
+        item: &CheckMenuItem,
+        id: String,
+    ) -> Result<(), ErrorCode> {
         self.inner
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))?;
+            .with(|menu| item.0.with(|item| menu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))?;
 
         self.register(id, AnyMenuItem::Check(item.0.clone()));
         Ok(())
     }
+
+    /// Like `appendCheckMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_check_menu_item_async(
+        &self,
+        item: &CheckMenuItem,
+        id: String,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.append_check_menu_item(&item, id))
+    }
     #[napi]
-    pub fn append_menu_item(&self, item: &MenuItem, id: Option<String>) -> Result<()> {
+    pub fn append_menu_item(&self, item: &MenuItem, id: Option<String>) -> Result<(), ErrorCode> {
         self.inner
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))?;
+            .with(|menu| item.0.with(|item| menu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))?;
 
         if let Some(id_str) = id {
             self.register(id_str, AnyMenuItem::Standard(item.0.clone()));
         }
         Ok(())
     }
+
+    /// Like `appendMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_menu_item_async(
+        &self,
+        item: &MenuItem,
+        id: Option<String>,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.append_menu_item(&item, id))
+    }
     #[napi]
-    pub fn append_submenu(&self, item: &Submenu, id: Option<String>) -> Result<()> {
+    pub fn append_submenu(&self, item: &Submenu, id: Option<String>) -> Result<(), ErrorCode> {
         self.inner
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))?;
+            .with(|menu| item.0.with(|item| menu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))?;
 
         if let Some(id_str) = id {
             self.register(id_str, AnyMenuItem::Submenu(item.0.clone()));
@@ -66,74 +117,137 @@ impl Menu {
         Ok(())
     }
 
+    /// Like `appendSubmenu`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_submenu_async(
+        &self,
+        item: &Submenu,
+        id: Option<String>,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.append_submenu(&item, id))
+    }
+
     #[napi]
-    pub fn append_icon_menu_item(&self, item: &IconMenuItem, id: String) -> Result<()> {
+    pub fn append_icon_menu_item(&self, item: &IconMenuItem, id: String) -> Result<(), ErrorCode> {
         self.inner
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))?;
+            .with(|menu| item.0.with(|item| menu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))?;
 
         self.register(id, AnyMenuItem::Icon(item.0.clone()));
         Ok(())
     }
 
+    /// Like `appendIconMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_icon_menu_item_async(
+        &self,
+        item: &IconMenuItem,
+        id: String,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.append_icon_menu_item(&item, id))
+    }
+
     #[napi]
-    pub fn append_predefined_menu_item(&self, item: &PredefinedMenuItem) -> Result<()> {
+    pub fn append_predefined_menu_item(&self, item: &PredefinedMenuItem) -> Result<(), ErrorCode> {
         self.inner
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|menu| item.0.with(|item| menu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
     }
+
+    /// Like `appendPredefinedMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_predefined_menu_item_async(
+        &self,
+        item: &PredefinedMenuItem,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.append_predefined_menu_item(&item))
+    }
+    /// Whether the check item registered as `id` is checked.
     #[napi]
-    pub fn is_checked(&self, id: String) -> bool {
+    pub fn is_checked(&self, id: String) -> Result<bool, ErrorCode> {
         let reg = self.registry.lock().unwrap();
-        if let Some(AnyMenuItem::Check(item)) = reg.get(&id) {
-            return item.is_checked();
+        match reg.get(&id) {
+            Some(AnyMenuItem::Check(item)) => item.with(|item| item.is_checked()),
+            Some(_) => Err(not_a_check_item(&id)),
+            None => Err(unknown_item(&id)),
         }
-        false
     }
+
+    /// Flips the check item registered as `id` and returns its new state.
     #[napi]
-    pub fn toggle_check(&self, id: String) -> bool {
+    pub fn toggle_check(&self, id: String) -> Result<bool, ErrorCode> {
         let reg = self.registry.lock().unwrap();
-        if let Some(AnyMenuItem::Check(item)) = reg.get(&id) {
-            let new_state = !item.is_checked();
-            item.set_checked(new_state);
-            return new_state;
+        match reg.get(&id) {
+            Some(AnyMenuItem::Check(item)) => item.with(|item| {
+                let new_state = !item.is_checked();
+                item.set_checked(new_state);
+                new_state
+            }),
+            Some(_) => Err(not_a_check_item(&id)),
+            None => Err(unknown_item(&id)),
         }
-        false
+    }
+
+    /// Like `toggleCheck`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn toggle_check_async(&self, id: String) -> AsyncTask<UiTask<bool>> {
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.toggle_check(id))
     }
 
     #[napi]
-    pub fn set_text(&self, id: String, text: String) {
+    pub fn set_text(&self, id: String, text: String) -> Result<(), ErrorCode> {
         let reg = self.registry.lock().unwrap();
-        if let Some(any_item) = reg.get(&id) {
-            match any_item {
-                AnyMenuItem::Standard(i) => i.set_text(text),
-                AnyMenuItem::Check(i) => i.set_text(text),
-                AnyMenuItem::Icon(i) => i.set_text(text),
-                AnyMenuItem::Submenu(i) => i.set_text(text),
-            }
+        match reg.get(&id).ok_or_else(|| unknown_item(&id))? {
+            AnyMenuItem::Standard(i) => i.with(|i| i.set_text(text)),
+            AnyMenuItem::Check(i) => i.with(|i| i.set_text(text)),
+            AnyMenuItem::Icon(i) => i.with(|i| i.set_text(text)),
+            AnyMenuItem::Submenu(i) => i.with(|i| i.set_text(text)),
         }
     }
-}
 
-impl Default for Menu {
-    fn default() -> Self {
-        Self::new()
+    /// Like `setText`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_text_async(&self, id: String, text: String) -> AsyncTask<UiTask<()>> {
+        let menu = self.clone();
+        ui_thread::run_async(move || menu.set_text(id, text))
     }
 }
 
 #[napi]
-pub struct MenuItem(pub(crate) tray_menu::MenuItem);
+#[derive(Clone)]
+pub struct MenuItem(pub(crate) UiBound<tray_menu::MenuItem>);
 
 #[napi]
 impl MenuItem {
     #[napi]
-    pub fn set_text(&self, text: String) {
-        self.0.set_text(text);
+    pub fn set_text(&self, text: String) -> Result<(), ErrorCode> {
+        self.0.with(|item| item.set_text(text))
+    }
+
+    /// Like `setText`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_text_async(&self, text: String) -> AsyncTask<UiTask<()>> {
+        let item = self.clone();
+        ui_thread::run_async(move || item.set_text(text))
     }
 
     #[napi]
-    pub fn set_enabled(&self, enabled: bool) {
-        self.0.set_enabled(enabled);
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), ErrorCode> {
+        self.0.with(|item| item.set_enabled(enabled))
+    }
+
+    /// Like `setEnabled`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_enabled_async(&self, enabled: bool) -> AsyncTask<UiTask<()>> {
+        let item = self.clone();
+        ui_thread::run_async(move || item.set_enabled(enabled))
     }
 }
 
@@ -175,17 +289,19 @@ impl MenuItemBuilder {
     }
 
     #[napi]
-    pub fn build(&self) -> Result<MenuItem> {
-        let item = if let Some(id) = &self.id {
-            tray_menu::MenuItem::with_id(
-                tray_menu::MenuId(id.clone()),
-                &self.text,
-                self.enabled,
-                None,
-            )
-        } else {
-            tray_menu::MenuItem::new(&self.text, self.enabled, None)
-        };
+    pub fn build(&self) -> Result<MenuItem, ErrorCode> {
+        let item = UiBound::new(|| {
+            if let Some(id) = &self.id {
+                tray_menu::MenuItem::with_id(
+                    tray_menu::MenuId(id.clone()),
+                    &self.text,
+                    self.enabled,
+                    None,
+                )
+            } else {
+                tray_menu::MenuItem::new(&self.text, self.enabled, None)
+            }
+        })?;
         Ok(MenuItem(item))
     }
 }
@@ -197,7 +313,8 @@ impl Default for MenuItemBuilder {
 }
 
 #[napi]
-pub struct CheckMenuItem(pub(crate) tray_menu::CheckMenuItem);
+#[derive(Clone)]
+pub struct CheckMenuItem(pub(crate) UiBound<tray_menu::CheckMenuItem>);
 
 #[napi]
 #[derive(Clone)]
@@ -245,18 +362,20 @@ impl CheckMenuItemBuilder {
     }
 
     #[napi]
-    pub fn build(&self) -> Result<CheckMenuItem> {
-        let item = if let Some(id) = &self.id {
-            tray_menu::CheckMenuItem::with_id(
-                tray_menu::MenuId(id.clone()),
-                &self.text,
-                self.enabled,
-                self.checked,
-                None,
-            )
-        } else {
-            tray_menu::CheckMenuItem::new(&self.text, self.enabled, self.checked, None)
-        };
+    pub fn build(&self) -> Result<CheckMenuItem, ErrorCode> {
+        let item = UiBound::new(|| {
+            if let Some(id) = &self.id {
+                tray_menu::CheckMenuItem::with_id(
+                    tray_menu::MenuId(id.clone()),
+                    &self.text,
+                    self.enabled,
+                    self.checked,
+                    None,
+                )
+            } else {
+                tray_menu::CheckMenuItem::new(&self.text, self.enabled, self.checked, None)
+            }
+        })?;
         Ok(CheckMenuItem(item))
     }
 }
@@ -268,56 +387,107 @@ impl Default for CheckMenuItemBuilder {
 }
 
 #[napi]
-pub struct Submenu(pub(crate) tray_menu::Submenu);
+#[derive(Clone)]
+pub struct Submenu(pub(crate) UiBound<tray_menu::Submenu>);
 
 #[napi]
 impl CheckMenuItem {
     #[napi]
-    pub fn is_checked(&self) -> bool {
-        self.0.is_checked()
+    pub fn is_checked(&self) -> Result<bool, ErrorCode> {
+        self.0.with(|item| item.is_checked())
     }
 
     #[napi]
-    pub fn set_checked(&self, checked: bool) {
-        self.0.set_checked(checked);
+    pub fn set_checked(&self, checked: bool) -> Result<(), ErrorCode> {
+        self.0.with(|item| item.set_checked(checked))
+    }
+
+    /// Like `setChecked`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_checked_async(&self, checked: bool) -> AsyncTask<UiTask<()>> {
+        let item = self.clone();
+        ui_thread::run_async(move || item.set_checked(checked))
     }
 }
 
 #[napi]
 impl Submenu {
     #[napi]
-    pub fn append_menu_item(&self, item: &MenuItem) -> Result<()> {
+    pub fn append_menu_item(&self, item: &MenuItem) -> Result<(), ErrorCode> {
         self.0
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|submenu| item.0.with(|item| submenu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
+    }
+
+    /// Like `appendMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_menu_item_async(&self, item: &MenuItem) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let submenu = self.clone();
+        ui_thread::run_async(move || submenu.append_menu_item(&item))
     }
 
     #[napi]
-    pub fn append_submenu(&self, item: &Submenu) -> Result<()> {
+    pub fn append_submenu(&self, item: &Submenu) -> Result<(), ErrorCode> {
         self.0
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|submenu| item.0.with(|item| submenu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
+    }
+
+    /// Like `appendSubmenu`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_submenu_async(&self, item: &Submenu) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let submenu = self.clone();
+        ui_thread::run_async(move || submenu.append_submenu(&item))
     }
 
     #[napi]
-    pub fn append_check_menu_item(&self, item: &CheckMenuItem) -> Result<()> {
+    pub fn append_check_menu_item(&self, item: &CheckMenuItem) -> Result<(), ErrorCode> {
         self.0
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|submenu| item.0.with(|item| submenu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
+    }
+
+    /// Like `appendCheckMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_check_menu_item_async(&self, item: &CheckMenuItem) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let submenu = self.clone();
+        ui_thread::run_async(move || submenu.append_check_menu_item(&item))
     }
 
     #[napi]
-    pub fn append_icon_menu_item(&self, item: &IconMenuItem) -> Result<()> {
+    pub fn append_icon_menu_item(&self, item: &IconMenuItem) -> Result<(), ErrorCode> {
         self.0
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|submenu| item.0.with(|item| submenu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
+    }
+
+    /// Like `appendIconMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_icon_menu_item_async(&self, item: &IconMenuItem) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let submenu = self.clone();
+        ui_thread::run_async(move || submenu.append_icon_menu_item(&item))
     }
 
     #[napi]
-    pub fn append_predefined_menu_item(&self, item: &PredefinedMenuItem) -> Result<()> {
+    pub fn append_predefined_menu_item(&self, item: &PredefinedMenuItem) -> Result<(), ErrorCode> {
         self.0
-            .append(&item.0)
-            .map_err(|e| Error::from_reason(format!("{e}")))
+            .with(|submenu| item.0.with(|item| submenu.append(item)))??
+            .map_err(platform_err("Failed to append menu item"))
+    }
+
+    /// Like `appendPredefinedMenuItem`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn append_predefined_menu_item_async(
+        &self,
+        item: &PredefinedMenuItem,
+    ) -> AsyncTask<UiTask<()>> {
+        let item = item.clone();
+        let submenu = self.clone();
+        ui_thread::run_async(move || submenu.append_predefined_menu_item(&item))
     }
 }
 
@@ -351,8 +521,10 @@ impl SubmenuBuilder {
     }
 
     #[napi]
-    pub fn build(&self) -> Result<Submenu> {
-        Ok(Submenu(tray_menu::Submenu::new(&self.text, self.enabled)))
+    pub fn build(&self) -> Result<Submenu, ErrorCode> {
+        Ok(Submenu(UiBound::new(|| {
+            tray_menu::Submenu::new(&self.text, self.enabled)
+        })?))
     }
 }
 
@@ -363,29 +535,47 @@ impl Default for SubmenuBuilder {
 }
 
 #[napi]
-pub struct PredefinedMenuItem(pub(crate) tray_menu::PredefinedMenuItem);
+#[derive(Clone)]
+pub struct PredefinedMenuItem(pub(crate) UiBound<tray_menu::PredefinedMenuItem>);
 
 #[napi]
 impl PredefinedMenuItem {
     #[napi]
-    pub fn separator() -> Self {
-        Self(tray_menu::PredefinedMenuItem::separator())
+    pub fn separator() -> Result<Self, ErrorCode> {
+        Ok(Self(UiBound::new(
+            tray_menu::PredefinedMenuItem::separator,
+        )?))
     }
 }
 
 #[napi]
-pub struct IconMenuItem(pub(crate) tray_menu::IconMenuItem);
+#[derive(Clone)]
+pub struct IconMenuItem(pub(crate) UiBound<tray_menu::IconMenuItem>);
 
 #[napi]
 impl IconMenuItem {
     #[napi]
-    pub fn set_text(&self, text: String) {
-        self.0.set_text(text);
+    pub fn set_text(&self, text: String) -> Result<(), ErrorCode> {
+        self.0.with(|item| item.set_text(text))
+    }
+
+    /// Like `setText`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_text_async(&self, text: String) -> AsyncTask<UiTask<()>> {
+        let item = self.clone();
+        ui_thread::run_async(move || item.set_text(text))
     }
 
     #[napi]
-    pub fn set_enabled(&self, enabled: bool) {
-        self.0.set_enabled(enabled);
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), ErrorCode> {
+        self.0.with(|item| item.set_enabled(enabled))
+    }
+
+    /// Like `setEnabled`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_enabled_async(&self, enabled: bool) -> AsyncTask<UiTask<()>> {
+        let item = self.clone();
+        ui_thread::run_async(move || item.set_enabled(enabled))
     }
 }
 
@@ -423,10 +613,12 @@ impl IconMenuItemBuilder {
     }
 
     #[napi]
-    pub fn with_icon(&mut self, icon: &Icon) -> Result<IconMenuItemBuilder> {
-        let tray_icon = tray_menu::Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height)
-            .map_err(|e| Error::from_reason(format!("Failed to create menu icon: {e}")))?;
-        self.icon = Some(tray_icon);
+    pub fn with_icon(
+        &mut self,
+        icon: Either<&Icon, &IconSet>,
+    ) -> Result<IconMenuItemBuilder, ErrorCode> {
+        let icon = resolve_icon(icon, IconTarget::Menu)?;
+        self.icon = Some(icon.menu_icon()?);
         Ok(self.clone())
     }
 
@@ -437,22 +629,24 @@ impl IconMenuItemBuilder {
     }
 
     #[napi]
-    pub fn build(&self) -> Result<IconMenuItem> {
+    pub fn build(&self) -> Result<IconMenuItem, ErrorCode> {
         let icon = self
             .icon
             .clone()
-            .ok_or_else(|| Error::from_reason("Icon is required".to_string()))?;
-        let item = if let Some(id) = &self.id {
-            tray_menu::IconMenuItem::with_id(
-                tray_menu::MenuId(id.clone()),
-                &self.text,
-                self.enabled,
-                Some(icon),
-                None,
-            )
-        } else {
-            tray_menu::IconMenuItem::new(&self.text, self.enabled, Some(icon), None)
-        };
+            .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, "Icon is required"))?;
+        let item = UiBound::new(|| {
+            if let Some(id) = &self.id {
+                tray_menu::IconMenuItem::with_id(
+                    tray_menu::MenuId(id.clone()),
+                    &self.text,
+                    self.enabled,
+                    Some(icon),
+                    None,
+                )
+            } else {
+                tray_menu::IconMenuItem::new(&self.text, self.enabled, Some(icon), None)
+            }
+        })?;
         Ok(IconMenuItem(item))
     }
 }
@@ -468,12 +662,115 @@ pub struct MenuEvent {
     pub id: String,
 }
 
+impl From<tray_menu::MenuEvent> for MenuEvent {
+    fn from(event: tray_menu::MenuEvent) -> Self {
+        Self { id: event.id.0 }
+    }
+}
+
+pub(crate) static MENU_EVENTS: EventHub<tray_menu::MenuEvent> = EventHub::new();
+
+pub(crate) fn install_menu_event_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        tray_menu::MenuEvent::set_event_handler(Some(|event| MENU_EVENTS.dispatch(event)));
+    });
+}
+
 #[napi]
 pub fn poll_menu_events() -> Option<MenuEvent> {
-    tray_menu::MenuEvent::receiver()
-        .try_recv()
-        .ok()
-        .map(|e| MenuEvent { id: e.id.0 })
+    install_menu_event_handler();
+    MENU_EVENTS.pop().map(std::convert::Into::into)
+}
+
+/// Removes up to `max` queued menu events (all of them when omitted), oldest first.
+#[napi]
+pub fn drain_menu_events(max: Option<u32>) -> Vec<MenuEvent> {
+    install_menu_event_handler();
+    MENU_EVENTS
+        .drain(max)
+        .into_iter()
+        .map(std::convert::Into::into)
+        .collect()
+}
+
+/// Registers a listener that receives every menu event as it happens.
+///
+/// Events delivered to a listener are no longer queued for `pollMenuEvents`.
+#[napi]
+pub fn on_menu_event(callback: Function<MenuEvent, ()>) -> Result<EventSubscription, ErrorCode> {
+    install_menu_event_handler();
+    let callback: EventCallback<MenuEvent> = callback
+        .build_threadsafe_function()
+        .callee_handled::<false>()
+        .weak::<true>()
+        .build()
+        .map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to register listener: {}", e.reason),
+            )
+        })?;
+    let id = MENU_EVENTS.subscribe(move |event: &tray_menu::MenuEvent| {
+        callback.call(
+            event.clone().into(),
+            ThreadsafeFunctionCallMode::NonBlocking,
+        ) == Status::Ok
+    });
+    Ok(EventSubscription::new(id, |id| MENU_EVENTS.unsubscribe(id)))
+}
+
+/// Resolves with the next menu event, or `null` if `timeoutMs` elapses first.
+#[napi(ts_return_type = "Promise<MenuEvent | null>")]
+pub fn next_menu_event(
+    env: &Env,
+    options: Option<WaitOptions>,
+) -> Result<PromiseRaw<'_, Option<MenuEvent>>, ErrorCode> {
+    install_menu_event_handler();
+    let timeout = events::timeout_of(options);
+    events::spawn(env, async move {
+        MENU_EVENTS
+            .next(|_| true, timeout)
+            .await
+            .map(std::convert::Into::into)
+    })
+}
+
+/// Returns an async iterator over menu events: `for await (const event of menuEvents())`.
+///
+/// With `timeoutMs` the iteration ends once no event arrives within that time.
+#[napi]
+pub fn menu_events(options: Option<WaitOptions>) -> MenuEventStream {
+    install_menu_event_handler();
+    MenuEventStream(EventStream::new(
+        MENU_EVENTS.subscription(|_| true, usize::MAX),
+        events::timeout_of(options),
+    ))
+}
+
+#[napi(async_iterator)]
+pub struct MenuEventStream(EventStream<tray_menu::MenuEvent>);
+
+#[napi]
+impl MenuEventStream {
+    /// Ends the iteration and stops receiving events.
+    #[napi]
+    pub fn close(&self) {
+        self.0.close();
+    }
+}
+
+impl AsyncGenerator for MenuEventStream {
+    type Yield = MenuEvent;
+    type Next = ();
+    type Return = ();
+
+    fn next(
+        &mut self,
+        _value: Option<Self::Next>,
+    ) -> impl Future<Output = Result<Option<Self::Yield>>> + Send + 'static {
+        self.0.next()
+    }
 }
 
 #[napi(object)]
@@ -0,0 +1,450 @@
+use crate::error::ErrorCode;
+use crate::icon::{check_size, parse_color, unit_interval, Icon};
+use ab_glyph::{point, Font, FontArc, Glyph, PxScale, ScaleFont};
+use image::RgbaImage;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::sync::LazyLock;
+
+/// DejaVu Sans Bold, used to draw text unless another font is given. See
+/// `assets/fonts/LICENSE`.
+static DEFAULT_FONT: LazyLock<FontArc> = LazyLock::new(|| {
+    FontArc::try_from_slice(include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf"))
+        .expect("embedded font is valid")
+});
+
+/// A single line of text laid out at a pixel size.
+pub(crate) struct TextRun {
+    font: FontArc,
+    glyphs: Vec<Glyph>,
+}
+
+impl TextRun {
+    pub(crate) fn new(font: Option<&FontArc>, text: &str, size: f32) -> Self {
+        let font = font.unwrap_or(&DEFAULT_FONT).clone();
+        let scaled = font.as_scaled(PxScale::from(size));
+        let mut glyphs = Vec::new();
+        let mut caret = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            glyphs.push(id.with_scale_and_position(size, point(caret, 0.0)));
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        Self { font, glyphs }
+    }
+
+    /// Bounds of the drawn pixels relative to the start of the baseline, as
+    /// `(min_x, min_y, max_x, max_y)`, or `None` for blank text.
+    fn ink_bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        self.glyphs
+            .iter()
+            .filter_map(|glyph| self.font.outline_glyph(glyph.clone()))
+            .map(|outlined| outlined.px_bounds())
+            .map(|rect| (rect.min.x, rect.min.y, rect.max.x, rect.max.y))
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+    }
+
+    /// Width and height of the drawn pixels.
+    pub(crate) fn ink_size(&self) -> (f32, f32) {
+        self.ink_bounds()
+            .map_or((0.0, 0.0), |(min_x, min_y, max_x, max_y)| {
+                (max_x - min_x, max_y - min_y)
+            })
+    }
+
+    /// Draws the text so that its drawn pixels are centered on `(center_x, center_y)`.
+    pub(crate) fn draw_centered(
+        &self,
+        canvas: &mut RgbaImage,
+        center_x: f32,
+        center_y: f32,
+        color: [u8; 4],
+    ) {
+        let Some((min_x, min_y, max_x, max_y)) = self.ink_bounds() else {
+            return;
+        };
+        // Whole pixel offsets keep glyph edges as crisp as the rasterizer drew them.
+        let offset_x = (center_x - (min_x + max_x) / 2.0).round();
+        let offset_y = (center_y - (min_y + max_y) / 2.0).round();
+        for glyph in &self.glyphs {
+            let mut glyph = glyph.clone();
+            glyph.position = point(glyph.position.x + offset_x, glyph.position.y + offset_y);
+            let Some(outlined) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, coverage| {
+                blend(
+                    canvas,
+                    bounds.min.x as i64 + i64::from(x),
+                    bounds.min.y as i64 + i64::from(y),
+                    color,
+                    coverage,
+                );
+            });
+        }
+    }
+}
+
+/// Composites `color` at `coverage` over the pixel at `(x, y)`, ignoring pixels off the canvas.
+pub(crate) fn blend(canvas: &mut RgbaImage, x: i64, y: i64, color: [u8; 4], coverage: f32) {
+    let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+        return;
+    };
+    if x >= canvas.width() || y >= canvas.height() {
+        return;
+    }
+    let source_alpha = f32::from(color[3]) / 255.0 * coverage.clamp(0.0, 1.0);
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let pixel = canvas.get_pixel_mut(x, y);
+    let target_alpha = f32::from(pixel[3]) / 255.0;
+    let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+    for channel in 0..3 {
+        let value = (f32::from(color[channel]) * source_alpha
+            + f32::from(pixel[channel]) * target_alpha * (1.0 - source_alpha))
+            / alpha;
+        pixel[channel] = value.round() as u8;
+    }
+    pixel[3] = (alpha * 255.0).round() as u8;
+}
+
+/// Fills an anti-aliased rectangle whose corners are rounded with `radius`.
+pub(crate) fn fill_rounded_rect(
+    canvas: &mut RgbaImage,
+    (x, y, width, height): (f32, f32, f32, f32),
+    radius: f32,
+    color: [u8; 4],
+) {
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    let (center_x, center_y) = (x + width / 2.0, y + height / 2.0);
+    for py in y.floor() as i64..(y + height).ceil() as i64 {
+        for px in x.floor() as i64..(x + width).ceil() as i64 {
+            // Signed distance from the pixel center to the rounded rectangle's edge.
+            let dx = (px as f32 + 0.5 - center_x).abs() - (width / 2.0 - radius);
+            let dy = (py as f32 + 0.5 - center_y).abs() - (height / 2.0 - radius);
+            let distance = dx.max(0.0).hypot(dy.max(0.0)) + dx.max(dy).min(0.0) - radius;
+            blend(canvas, px, py, color, 0.5 - distance);
+        }
+    }
+}
+
+/// Loads a TrueType/OpenType font from a file path or its contents.
+pub(crate) fn load_font(source: Either<String, Buffer>) -> Result<FontArc, ErrorCode> {
+    let data = match source {
+        Either::A(path) => std::fs::read(&path).map_err(|e| {
+            Error::new(
+                ErrorCode::InvalidArgument,
+                format!("Failed to read font {path}: {e}"),
+            )
+        })?,
+        Either::B(bytes) => bytes.to_vec(),
+    };
+    FontArc::try_from_vec(data)
+        .map_err(|e| Error::new(ErrorCode::InvalidArgument, format!("Invalid font: {e}")))
+}
+
+#[napi(object)]
+pub struct TextIconOptions {
+    /// Width and height of the icon in pixels, 32 by default.
+    pub size: Option<u32>,
+    /// Path or contents of a TrueType/OpenType font. DejaVu Sans Bold is used by default.
+    pub font: Option<Either<String, Buffer>>,
+    /// Text color, `#ffffff` by default.
+    pub color: Option<String>,
+    /// Background color. Transparent by default.
+    pub background: Option<String>,
+}
+
+/// Renders `text` as large as fits, centered on a square canvas.
+pub(crate) fn text_icon(text: &str, options: TextIconOptions) -> Result<Icon, ErrorCode> {
+    let size = options.size.unwrap_or(32);
+    check_size(size, size)?;
+    let font = options.font.map(load_font).transpose()?;
+    let color = parse_color(options.color.as_deref().unwrap_or("#ffffff"))?;
+    let background = options
+        .background
+        .as_deref()
+        .map(parse_color)
+        .transpose()?
+        .unwrap_or([0, 0, 0, 0]);
+
+    let mut canvas = RgbaImage::from_pixel(size, size, image::Rgba(background));
+    let available = size as f32 * 0.9;
+    let mut text_size = size as f32 * 0.8;
+    let mut run = TextRun::new(font.as_ref(), text, text_size);
+    let (width, height) = run.ink_size();
+    let largest = width.max(height);
+    if largest > available {
+        text_size *= available / largest;
+        run = TextRun::new(font.as_ref(), text, text_size);
+    }
+    let center = size as f32 / 2.0;
+    run.draw_centered(&mut canvas, center, center, color);
+    Icon::from_rgba_vec(canvas.into_raw(), size, size)
+}
+
+#[napi(string_enum = "camelCase")]
+#[derive(Clone, Copy)]
+pub enum BadgePosition {
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+}
+
+#[napi(object)]
+pub struct BadgeOptions {
+    /// Text shown in the badge. Takes precedence over `count`.
+    pub text: Option<String>,
+    /// Number shown in the badge. No badge is drawn for 0.
+    pub count: Option<u32>,
+    /// Counts above this are shown as e.g. `99+`. Defaults to 99.
+    pub max: Option<u32>,
+    /// Badge color, `#e53935` by default.
+    pub color: Option<String>,
+    /// Text color, `#ffffff` by default.
+    pub text_color: Option<String>,
+    /// Corner the badge is drawn in, `topRight` by default.
+    pub position: Option<BadgePosition>,
+}
+
+/// A validated badge, ready to be drawn on any icon.
+#[derive(Clone)]
+pub(crate) struct Badge {
+    label: String,
+    background: [u8; 4],
+    foreground: [u8; 4],
+    position: BadgePosition,
+}
+
+impl Badge {
+    /// Validates `options`, returning `None` when there is nothing to show.
+    pub(crate) fn from_options(options: BadgeOptions) -> Result<Option<Self>, ErrorCode> {
+        let label = match (options.text, options.count) {
+            (Some(text), _) => text,
+            (None, Some(0)) | (None, None) => return Ok(None),
+            (None, Some(count)) => {
+                let max = options.max.unwrap_or(99);
+                if count > max {
+                    format!("{max}+")
+                } else {
+                    count.to_string()
+                }
+            }
+        };
+        if label.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            label,
+            background: parse_color(options.color.as_deref().unwrap_or("#e53935"))?,
+            foreground: parse_color(options.text_color.as_deref().unwrap_or("#ffffff"))?,
+            position: options.position.unwrap_or(BadgePosition::TopRight),
+        }))
+    }
+
+    /// A badge showing `count` with the default look.
+    pub(crate) fn count(count: u32) -> Result<Option<Self>, ErrorCode> {
+        Self::from_options(BadgeOptions {
+            text: None,
+            count: Some(count),
+            max: None,
+            color: None,
+            text_color: None,
+            position: None,
+        })
+    }
+
+    /// Draws the badge onto a copy of `icon`.
+    pub(crate) fn draw(&self, icon: &Icon) -> Result<Icon, ErrorCode> {
+        let mut canvas = icon.to_image();
+        let (width, height) = (icon.width as f32, icon.height as f32);
+        let badge_height = (width.min(height) * 0.5).max(6.0);
+        let padding = badge_height * 0.25;
+
+        let mut text_size = badge_height * 0.7;
+        let mut text = TextRun::new(None, &self.label, text_size);
+        let (text_width, _) = text.ink_size();
+        let available = width - 2.0 * padding;
+        if text_width > available && text_width > 0.0 {
+            // Shrink long labels such as "99+" so the badge still fits the icon.
+            text_size *= available / text_width;
+            text = TextRun::new(None, &self.label, text_size);
+        }
+        let badge_width = (text.ink_size().0 + 2.0 * padding)
+            .max(badge_height)
+            .min(width);
+
+        let x = match self.position {
+            BadgePosition::TopLeft | BadgePosition::BottomLeft => 0.0,
+            BadgePosition::TopRight | BadgePosition::BottomRight => width - badge_width,
+        };
+        let y = match self.position {
+            BadgePosition::TopLeft | BadgePosition::TopRight => 0.0,
+            BadgePosition::BottomLeft | BadgePosition::BottomRight => height - badge_height,
+        };
+        fill_rounded_rect(
+            &mut canvas,
+            (x, y, badge_width, badge_height),
+            badge_height / 2.0,
+            self.background,
+        );
+        text.draw_centered(
+            &mut canvas,
+            x + badge_width / 2.0,
+            y + badge_height / 2.0,
+            self.foreground,
+        );
+        Icon::from_rgba_vec(canvas.into_raw(), icon.width, icon.height)
+    }
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy)]
+pub enum ProgressStyle {
+    Ring,
+    Bar,
+}
+
+#[napi(object)]
+pub struct ProgressOptions {
+    /// `ring` around the icon (the default) or `bar` along its bottom edge.
+    pub style: Option<ProgressStyle>,
+    /// Color of the completed part, `#2196f3` by default.
+    pub color: Option<String>,
+    /// Color of the remaining part, translucent black by default.
+    pub track_color: Option<String>,
+}
+
+/// A validated progress indicator, ready to be drawn on any icon.
+#[derive(Clone)]
+pub(crate) struct Progress {
+    fraction: f32,
+    style: ProgressStyle,
+    color: [u8; 4],
+    track: [u8; 4],
+}
+
+impl Progress {
+    pub(crate) fn new(fraction: f64, options: Option<ProgressOptions>) -> Result<Self, ErrorCode> {
+        let fraction = unit_interval("Progress", fraction)? as f32;
+        let (style, color, track) = match options {
+            Some(options) => (options.style, options.color, options.track_color),
+            None => (None, None, None),
+        };
+        Ok(Self {
+            fraction,
+            style: style.unwrap_or(ProgressStyle::Ring),
+            color: parse_color(color.as_deref().unwrap_or("#2196f3"))?,
+            track: parse_color(track.as_deref().unwrap_or("#00000066"))?,
+        })
+    }
+
+    /// Draws the indicator onto a copy of `icon`.
+    pub(crate) fn draw(&self, icon: &Icon) -> Result<Icon, ErrorCode> {
+        let mut canvas = icon.to_image();
+        let (width, height) = (icon.width as f32, icon.height as f32);
+        match self.style {
+            ProgressStyle::Ring => self.draw_ring(&mut canvas, width, height),
+            ProgressStyle::Bar => {
+                let bar_height = (height * 0.2).max(2.0);
+                let bar = (0.0, height - bar_height, width, bar_height);
+                fill_rounded_rect(&mut canvas, bar, bar_height / 2.0, self.track);
+                if self.fraction > 0.0 {
+                    let done = (bar.0, bar.1, width * self.fraction, bar_height);
+                    fill_rounded_rect(&mut canvas, done, bar_height / 2.0, self.color);
+                }
+            }
+        }
+        Icon::from_rgba_vec(canvas.into_raw(), icon.width, icon.height)
+    }
+
+    /// A ring along the icon's edge, filled clockwise from 12 o'clock.
+    fn draw_ring(&self, canvas: &mut RgbaImage, width: f32, height: f32) {
+        let (center_x, center_y) = (width / 2.0, height / 2.0);
+        let outer = width.min(height) / 2.0;
+        let thickness = (outer * 0.22).max(1.5);
+        let middle = outer - thickness / 2.0;
+        let sweep = self.fraction * std::f32::consts::TAU;
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let dx = x as f32 + 0.5 - center_x;
+                let dy = y as f32 + 0.5 - center_y;
+                let coverage = thickness / 2.0 - (dx.hypot(dy) - middle).abs() + 0.5;
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let angle = dx.atan2(-dy).rem_euclid(std::f32::consts::TAU);
+                let color = if angle <= sweep {
+                    self.color
+                } else {
+                    self.track
+                };
+                blend(canvas, i64::from(x), i64::from(y), color, coverage);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Progress, ProgressOptions, ProgressStyle};
+    use crate::icon::Icon;
+
+    const SIZE: u32 = 20;
+
+    fn draw(fraction: f64, style: ProgressStyle) -> Vec<u8> {
+        let icon = Icon::from_rgba_vec(vec![0; (SIZE * SIZE * 4) as usize], SIZE, SIZE).unwrap();
+        let options = ProgressOptions {
+            style: Some(style),
+            color: Some("#ff0000".to_string()),
+            track_color: Some("#000000".to_string()),
+        };
+        let drawn = Progress::new(fraction, Some(options))
+            .unwrap()
+            .draw(&icon)
+            .unwrap();
+        drawn.rgba.to_vec()
+    }
+
+    fn pixel(rgba: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * SIZE + x) * 4) as usize;
+        rgba[i..i + 4].try_into().unwrap()
+    }
+
+    const DONE: [u8; 4] = [255, 0, 0, 255];
+    const TRACK: [u8; 4] = [0, 0, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn draws_a_bar_along_the_bottom() {
+        let rgba = draw(0.5, ProgressStyle::Bar);
+        assert_eq!(pixel(&rgba, 5, 18), DONE);
+        assert_eq!(pixel(&rgba, 15, 18), TRACK);
+        assert_eq!(pixel(&rgba, 10, 5), CLEAR);
+
+        let rgba = draw(0.0, ProgressStyle::Bar);
+        assert_eq!(pixel(&rgba, 5, 18), TRACK);
+    }
+
+    #[test]
+    fn fills_the_ring_clockwise_from_the_top() {
+        let rgba = draw(0.25, ProgressStyle::Ring);
+        // Just right of 12 o'clock, then 3, 6 and 9 o'clock.
+        assert_eq!(pixel(&rgba, 10, 1), DONE);
+        assert_eq!(pixel(&rgba, 18, 12), TRACK);
+        assert_eq!(pixel(&rgba, 9, 18), TRACK);
+        assert_eq!(pixel(&rgba, 1, 9), TRACK);
+        assert_eq!(pixel(&rgba, 10, 10), CLEAR);
+
+        let rgba = draw(1.0, ProgressStyle::Ring);
+        assert_eq!(pixel(&rgba, 1, 9), DONE);
+    }
+}
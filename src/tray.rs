@@ -1,22 +1,65 @@
+use crate::animation::{Animation, AnimationOptions};
 use crate::common::{MouseButton, MouseButtonState, Rect};
-use crate::icon::Icon;
+use crate::error::ErrorCode;
+use crate::event_loop;
+use crate::events::{self, EventCallback, EventHub, EventStream, EventSubscription, WaitOptions};
+use crate::icon::{resolve_icon, Icon, IconSet, IconTarget};
 use crate::menu::Menu;
+use crate::render::{Badge, BadgeOptions, Progress, ProgressOptions};
+use crate::ui_thread::{self, UiBound, UiTask};
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
 use napi_derive::napi;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, LazyLock, Mutex, Once};
+use std::time::{Duration, Instant};
+use tray_icon::menu::ContextMenu;
 use tray_icon::{
     TrayIcon as RawTrayIcon, TrayIconBuilder as RawTrayIconBuilder,
     TrayIconEvent as RawTrayIconEvent,
 };
 
-#[napi(object)]
-pub struct TrayIconEvent {
-    pub event_type: String,
-    pub id: String,
-    pub x: f64,
-    pub y: f64,
-    pub icon_rect: Rect,
-    pub button: MouseButton,
-    pub button_state: MouseButtonState,
+/// A tray icon event. `type` tells the variants apart, so TypeScript can narrow on it and only
+/// the fields that are meaningful for that kind of event are present.
+#[napi(discriminant = "type", discriminant_case = "kebab-case")]
+pub enum TrayIconEvent {
+    Click {
+        id: String,
+        x: f64,
+        y: f64,
+        icon_rect: Rect,
+        button: MouseButton,
+        button_state: MouseButtonState,
+    },
+    /// Only emitted on Windows.
+    DoubleClick {
+        id: String,
+        x: f64,
+        y: f64,
+        icon_rect: Rect,
+        button: MouseButton,
+    },
+    Enter {
+        id: String,
+        x: f64,
+        y: f64,
+        icon_rect: Rect,
+    },
+    Move {
+        id: String,
+        x: f64,
+        y: f64,
+        icon_rect: Rect,
+    },
+    Leave {
+        id: String,
+        x: f64,
+        y: f64,
+        icon_rect: Rect,
+    },
+    /// An event kind added by a newer tray-icon release that this binding does not know yet.
+    Unknown { id: String },
 }
 
 impl From<RawTrayIconEvent> for TrayIconEvent {
@@ -28,8 +71,7 @@ impl From<RawTrayIconEvent> for TrayIconEvent {
                 rect,
                 button,
                 button_state,
-            } => Self {
-                event_type: "click".to_string(),
+            } => Self::Click {
                 id: id.0,
                 x: position.x,
                 y: position.y,
@@ -42,114 +84,701 @@ impl From<RawTrayIconEvent> for TrayIconEvent {
                 position,
                 rect,
                 button,
-            } => Self {
-                event_type: "double-click".to_string(),
+            } => Self::DoubleClick {
                 id: id.0,
                 x: position.x,
                 y: position.y,
                 icon_rect: rect.into(),
                 button: button.into(),
-                button_state: tray_icon::MouseButtonState::Down.into(),
             },
-            RawTrayIconEvent::Enter { id, position, rect } => Self {
-                event_type: "enter".to_string(),
+            RawTrayIconEvent::Enter { id, position, rect } => Self::Enter {
                 id: id.0,
                 x: position.x,
                 y: position.y,
                 icon_rect: rect.into(),
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
             },
-            RawTrayIconEvent::Move { id, position, rect } => Self {
-                event_type: "move".to_string(),
+            RawTrayIconEvent::Move { id, position, rect } => Self::Move {
                 id: id.0,
                 x: position.x,
                 y: position.y,
                 icon_rect: rect.into(),
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
             },
-            RawTrayIconEvent::Leave { id, position, rect } => Self {
-                event_type: "leave".to_string(),
+            RawTrayIconEvent::Leave { id, position, rect } => Self::Leave {
                 id: id.0,
                 x: position.x,
                 y: position.y,
                 icon_rect: rect.into(),
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
             },
-            _ => Self {
-                event_type: "unknown".to_string(),
-                id: String::new(),
-                x: 0.0,
-                y: 0.0,
-                icon_rect: Rect {
-                    x: 0.0,
-                    y: 0.0,
-                    width: 0.0,
-                    height: 0.0,
-                },
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
+            event => Self::Unknown {
+                id: event.id().0.clone(),
             },
         }
     }
 }
 
+pub(crate) static TRAY_EVENTS: EventHub<RawTrayIconEvent> = EventHub::new();
+
+pub(crate) fn install_tray_event_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        RawTrayIconEvent::set_event_handler(Some(|event| TRAY_EVENTS.dispatch(event)));
+    });
+}
+
+#[napi(object)]
+pub struct PollOptions {
+    /// Only return events of the tray icon with this id. Events of other tray icons stay queued.
+    pub tray_id: Option<String>,
+}
+
+#[napi]
+pub fn poll_tray_events(options: Option<PollOptions>) -> Option<TrayIconEvent> {
+    install_tray_event_handler();
+    match options.and_then(|options| options.tray_id) {
+        Some(tray_id) => TRAY_EVENTS.pop_matching(|event| event.id() == tray_id),
+        None => TRAY_EVENTS.pop(),
+    }
+    .map(std::convert::Into::into)
+}
+
+/// Removes up to `max` queued tray events (all of them when omitted), oldest first.
 #[napi]
-pub fn poll_tray_events() -> Option<TrayIconEvent> {
-    tray_icon::TrayIconEvent::receiver()
-        .try_recv()
-        .ok()
+pub fn drain_tray_events(max: Option<u32>) -> Vec<TrayIconEvent> {
+    install_tray_event_handler();
+    TRAY_EVENTS
+        .drain(max)
+        .into_iter()
         .map(std::convert::Into::into)
+        .collect()
 }
 
+/// Registers a listener that receives every tray event as it happens.
+///
+/// Events delivered to a listener are no longer queued for `pollTrayEvents`.
 #[napi]
-pub struct TrayIcon(Option<RawTrayIcon>);
+pub fn on_tray_event(
+    callback: Function<TrayIconEvent, ()>,
+) -> Result<EventSubscription, ErrorCode> {
+    install_tray_event_handler();
+    subscribe_callback(callback, |_| true)
+}
 
+fn subscribe_callback(
+    callback: Function<TrayIconEvent, ()>,
+    filter: impl Fn(&RawTrayIconEvent) -> bool + Send + Sync + 'static,
+) -> Result<EventSubscription, ErrorCode> {
+    let callback: EventCallback<TrayIconEvent> = callback
+        .build_threadsafe_function()
+        .callee_handled::<false>()
+        .weak::<true>()
+        .build()
+        .map_err(|e| {
+            Error::new(
+                ErrorCode::PlatformError,
+                format!("Failed to register listener: {}", e.reason),
+            )
+        })?;
+    let id = TRAY_EVENTS.subscribe(move |event: &RawTrayIconEvent| {
+        filter(event)
+            && callback.call(
+                event.clone().into(),
+                ThreadsafeFunctionCallMode::NonBlocking,
+            ) == Status::Ok
+    });
+    Ok(EventSubscription::new(id, |id| TRAY_EVENTS.unsubscribe(id)))
+}
+
+/// Resolves with the next tray event, or `null` if `timeoutMs` elapses first.
+#[napi(ts_return_type = "Promise<TrayIconEvent | null>")]
+pub fn next_tray_event(
+    env: &Env,
+    options: Option<WaitOptions>,
+) -> Result<PromiseRaw<'_, Option<TrayIconEvent>>, ErrorCode> {
+    install_tray_event_handler();
+    let timeout = events::timeout_of(options);
+    events::spawn(env, async move {
+        TRAY_EVENTS
+            .next(|_| true, timeout)
+            .await
+            .map(std::convert::Into::into)
+    })
+}
+
+/// Returns an async iterator over tray events: `for await (const event of trayEvents())`.
+///
+/// With `timeoutMs` the iteration ends once no event arrives within that time.
 #[napi]
-impl TrayIcon {
+pub fn tray_events(options: Option<WaitOptions>) -> TrayEventStream {
+    install_tray_event_handler();
+    TrayEventStream::subscribe(|_| true, options)
+}
+
+#[napi(async_iterator)]
+pub struct TrayEventStream(EventStream<RawTrayIconEvent>);
+
+#[napi]
+impl TrayEventStream {
+    fn subscribe(
+        filter: impl Fn(&RawTrayIconEvent) -> bool + Send + Sync + 'static,
+        options: Option<WaitOptions>,
+    ) -> Self {
+        Self(EventStream::new(
+            TRAY_EVENTS.subscription(filter, usize::MAX),
+            events::timeout_of(options),
+        ))
+    }
+
+    /// Ends the iteration and stops receiving events.
     #[napi]
-    pub fn set_icon(&mut self, icon: Option<&Icon>) -> Result<()> {
-        if let Some(tray) = &self.0 {
-            tray.set_icon(icon.map(|i| i.inner.clone()))
-                .map_err(|e| Error::from_reason(format!("Failed to set icon: {e}")))?;
+    pub fn close(&self) {
+        self.0.close();
+    }
+}
+
+impl AsyncGenerator for TrayEventStream {
+    type Yield = TrayIconEvent;
+    type Next = ();
+    type Return = ();
+
+    fn next(
+        &mut self,
+        _value: Option<Self::Next>,
+    ) -> impl Future<Output = Result<Option<Self::Yield>>> + Send + 'static {
+        self.0.next()
+    }
+}
+
+/// Native state of a tray icon that has not been destroyed yet.
+pub(crate) struct TrayState {
+    pub(crate) tray: UiBound<RawTrayIcon>,
+    attributes: Mutex<TrayAttributes>,
+}
+
+/// What was last applied to a tray icon, since tray-icon cannot read it back.
+struct TrayAttributes {
+    icon: Option<Icon>,
+    tooltip: Option<String>,
+    title: Option<String>,
+    visible: bool,
+    menu: Option<Menu>,
+    badge: Option<Badge>,
+    animation: Option<Animation>,
+    progress: Option<Progress>,
+    /// When the icon was last redrawn for a progress update, see `PROGRESS_INTERVAL`.
+    progress_shown_at: Option<Instant>,
+    /// A progress update arrived too soon after the previous one and still has to be shown.
+    progress_pending: bool,
+}
+
+/// Minimum time between two icon updates caused by `setProgress`.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+impl TrayState {
+    /// Shows `icon` with the progress indicator and `badge` drawn on top of it.
+    fn show_icon(
+        &self,
+        icon: Option<&Icon>,
+        progress: Option<&Progress>,
+        badge: Option<&Badge>,
+    ) -> Result<(), ErrorCode> {
+        let shown = match icon {
+            Some(icon) => {
+                let mut shown = icon.clone();
+                if let Some(progress) = progress {
+                    shown = progress.draw(&shown)?;
+                }
+                if let Some(badge) = badge {
+                    shown = badge.draw(&shown)?;
+                }
+                Some(shown)
+            }
+            None => None,
+        };
+        let shown = shown.map(|icon| icon.tray_icon()).transpose()?;
+        self.tray
+            .with(|tray| tray.set_icon(shown))?
+            .map_err(|e| Error::new(ErrorCode::PlatformError, format!("Failed to set icon: {e}")))
+    }
+}
+
+/// Every tray icon that has been built and not destroyed, keyed by id.
+///
+/// Trays stay alive here until `destroy()` is called, no matter whether the JS `TrayIcon`
+/// object is garbage-collected in the meantime.
+static TRAYS: LazyLock<Mutex<HashMap<String, Arc<TrayState>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Shows the next frame of every animation and the progress updates that are due. Returns
+/// whether more are to come.
+pub(crate) fn tick() -> bool {
+    let trays: Vec<Arc<TrayState>> = TRAYS.lock().unwrap().values().cloned().collect();
+    let now = Instant::now();
+    let mut busy = false;
+    for state in trays {
+        let due = state.attributes.lock().unwrap().due(now);
+        busy |= due.busy;
+        // Nobody to report a failure to from here; the next update is tried anyway.
+        if let Some(frame) = due.frame {
+            let _ = state.tray.with(|tray| tray.set_icon(Some(frame)));
+        }
+        if let Some((icon, progress, badge)) = due.redraw {
+            let _ = state.show_icon(icon.as_ref(), progress.as_ref(), badge.as_ref());
+        }
+    }
+    busy
+}
+
+/// What `tick` has to show for one tray icon.
+struct Due {
+    /// The next animation frame.
+    frame: Option<tray_icon::Icon>,
+    /// The icon, progress and badge to redraw for a held-back progress update.
+    redraw: Option<(Option<Icon>, Option<Progress>, Option<Badge>)>,
+    /// Whether more updates are to come.
+    busy: bool,
+}
+
+impl TrayAttributes {
+    fn due(&mut self, now: Instant) -> Due {
+        let mut frame = None;
+        let mut busy = false;
+        if let Some(animation) = &mut self.animation {
+            frame = animation.advance(now);
+            if animation.is_finished() {
+                self.animation = None;
+                // The frames were drawn without the progress; show it now that they are done.
+                self.progress_pending |= self.progress.is_some();
+            } else {
+                busy = true;
+            }
+        }
+        let mut redraw = None;
+        if self.progress_pending && self.animation.is_none() {
+            let due = self
+                .progress_shown_at
+                .is_none_or(|shown_at| now >= shown_at + PROGRESS_INTERVAL);
+            if due {
+                self.progress_pending = false;
+                self.progress_shown_at = Some(now);
+                redraw = Some((self.icon.clone(), self.progress.clone(), self.badge.clone()));
+            } else {
+                busy = true;
+            }
         }
+        if redraw.is_some() {
+            // The redraw replaces the last frame right away.
+            frame = None;
+        }
+        Due {
+            frame,
+            redraw,
+            busy,
+        }
+    }
+}
+
+/// Number of tray icons that have been built and not destroyed yet.
+pub(crate) fn live_tray_count() -> usize {
+    TRAYS.lock().unwrap().len()
+}
+
+#[napi]
+#[derive(Clone)]
+pub struct TrayIcon {
+    id: String,
+}
+
+#[napi]
+impl TrayIcon {
+    /// Looks up the native state, failing once the tray icon has been destroyed.
+    ///
+    /// The registry lock is released before returning so calls into the UI thread never hold it.
+    fn state(&self) -> Result<Arc<TrayState>, ErrorCode> {
+        TRAYS.lock().unwrap().get(&self.id).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorCode::TrayDestroyed,
+                format!("Tray icon {} has been destroyed", self.id),
+            )
+        })
+    }
+
+    #[napi]
+    pub fn set_icon(&self, icon: Option<Either<&Icon, &IconSet>>) -> Result<(), ErrorCode> {
+        let icon = icon
+            .map(|icon| resolve_icon(icon, IconTarget::Tray))
+            .transpose()?;
+        self.replace_icon(icon)
+    }
+
+    /// Like `setIcon`, but returns a Promise. In threaded mode the JS thread does not wait for
+    /// GTK to apply it.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_icon_async(&self, icon: Option<Either<&Icon, &IconSet>>) -> AsyncTask<UiTask<()>> {
+        let icon = icon
+            .map(|icon| resolve_icon(icon, IconTarget::Tray))
+            .transpose();
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.replace_icon(icon?))
+    }
+
+    fn replace_icon(&self, icon: Option<Icon>) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        let (progress, badge) = {
+            let mut attributes = state.attributes.lock().unwrap();
+            attributes.animation = None;
+            (attributes.progress.clone(), attributes.badge.clone())
+        };
+        state.show_icon(icon.as_ref(), progress.as_ref(), badge.as_ref())?;
+        state.attributes.lock().unwrap().icon = icon;
         Ok(())
     }
 
+    /// Draws a badge on the current icon, e.g. an unread count, or removes it when `null` or 0.
+    ///
+    /// The badge is kept when the icon is changed with `setIcon`.
     #[napi]
-    pub fn set_tooltip(&mut self, tooltip: Option<String>) -> Result<()> {
-        if let Some(tray) = &self.0 {
-            let _ = tray.set_tooltip(tooltip);
-        }
+    pub fn set_badge(&self, badge: Option<Either<u32, BadgeOptions>>) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        let badge = match badge {
+            Some(Either::A(count)) => Badge::count(count)?,
+            Some(Either::B(options)) => Badge::from_options(options)?,
+            None => None,
+        };
+        let (icon, progress) = {
+            let mut attributes = state.attributes.lock().unwrap();
+            if let Some(animation) = &mut attributes.animation {
+                // The next frame shows the new badge.
+                animation.set_badge(badge.as_ref())?;
+                attributes.badge = badge;
+                return Ok(());
+            }
+            (attributes.icon.clone(), attributes.progress.clone())
+        };
+        state.show_icon(icon.as_ref(), progress.as_ref(), badge.as_ref())?;
+        state.attributes.lock().unwrap().badge = badge;
         Ok(())
     }
 
+    /// Like `setBadge`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_badge_async(
+        &self,
+        badge: Option<Either<u32, BadgeOptions>>,
+    ) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_badge(badge))
+    }
+
+    /// Draws a progress ring or bar from 0 to 1 over the current icon, or removes it when
+    /// `null`.
+    ///
+    /// The icon is redrawn at most every 100ms. A value held back by that is shown by the native
+    /// timer that also drives animations once the platform event loop runs, without another
+    /// call. While an animation plays, the progress shows once it is stopped or has ended.
     #[napi]
-    pub fn set_title(&mut self, title: Option<String>) -> Result<()> {
-        if let Some(tray) = &self.0 {
-            tray.set_title(title);
-        }
+    pub fn set_progress(
+        &self,
+        fraction: Option<f64>,
+        options: Option<ProgressOptions>,
+    ) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        let progress = fraction
+            .map(|fraction| Progress::new(fraction, options))
+            .transpose()?;
+        let now = Instant::now();
+        let (icon, badge) = {
+            let mut attributes = state.attributes.lock().unwrap();
+            let throttled = progress.is_some()
+                && attributes
+                    .progress_shown_at
+                    .is_some_and(|shown_at| now < shown_at + PROGRESS_INTERVAL);
+            attributes.progress = progress.clone();
+            if attributes.animation.is_some() {
+                return Ok(());
+            }
+            if throttled {
+                attributes.progress_pending = true;
+                drop(attributes);
+                ui_thread::schedule_ticks(tick);
+                return Ok(());
+            }
+            attributes.progress_pending = false;
+            attributes.progress_shown_at = Some(now);
+            (attributes.icon.clone(), attributes.badge.clone())
+        };
+        state.show_icon(icon.as_ref(), progress.as_ref(), badge.as_ref())
+    }
+
+    /// Like `setProgress`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_progress_async(
+        &self,
+        fraction: Option<f64>,
+        options: Option<ProgressOptions>,
+    ) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_progress(fraction, options))
+    }
+
+    /// Cycles through `frames` natively, without a JS timer. Replaces a running animation.
+    ///
+    /// Stops when `stopAnimation()` or `setIcon()` is called, or after the last frame unless
+    /// looping.
+    #[napi]
+    pub fn play_animation(
+        &self,
+        frames: Vec<&Icon>,
+        options: Option<AnimationOptions>,
+    ) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        let badge = state.attributes.lock().unwrap().badge.clone();
+        let frames = frames.into_iter().cloned().collect();
+        let animation = Animation::new(frames, options, badge.as_ref())?;
+        let first = animation.current_frame();
+        state.attributes.lock().unwrap().animation = Some(animation);
+        state
+            .tray
+            .with(|tray| tray.set_icon(Some(first)))?
+            .map_err(|e| {
+                Error::new(ErrorCode::PlatformError, format!("Failed to set icon: {e}"))
+            })?;
+        ui_thread::schedule_ticks(tick);
         Ok(())
     }
 
+    /// Stops the running animation and shows the icon set with `setIcon()` again.
     #[napi]
-    pub fn set_visible(&mut self, visible: bool) -> Result<()> {
-        if let Some(tray) = &self.0 {
-            let _ = tray.set_visible(visible);
-        }
+    pub fn stop_animation(&self) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        let (icon, progress, badge) = {
+            let mut attributes = state.attributes.lock().unwrap();
+            if attributes.animation.take().is_none() {
+                return Ok(());
+            }
+            attributes.progress_pending = false;
+            (
+                attributes.icon.clone(),
+                attributes.progress.clone(),
+                attributes.badge.clone(),
+            )
+        };
+        state.show_icon(icon.as_ref(), progress.as_ref(), badge.as_ref())
+    }
+
+    #[napi]
+    pub fn is_animating(&self) -> Result<bool, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().animation.is_some())
+    }
+
+    #[napi]
+    pub fn set_tooltip(&self, tooltip: Option<String>) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        state
+            .tray
+            .with(|tray| tray.set_tooltip(tooltip.as_ref()))?
+            .map_err(|e| {
+                Error::new(
+                    ErrorCode::PlatformError,
+                    format!("Failed to set tooltip: {e}"),
+                )
+            })?;
+        state.attributes.lock().unwrap().tooltip = tooltip;
         Ok(())
     }
+
+    /// Like `setTooltip`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_tooltip_async(&self, tooltip: Option<String>) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_tooltip(tooltip))
+    }
+
+    #[napi]
+    pub fn set_title(&self, title: Option<String>) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        state.tray.with(|tray| tray.set_title(title.as_ref()))?;
+        state.attributes.lock().unwrap().title = title;
+        Ok(())
+    }
+
+    /// Like `setTitle`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_title_async(&self, title: Option<String>) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_title(title))
+    }
+
+    #[napi]
+    pub fn set_visible(&self, visible: bool) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        state
+            .tray
+            .with(|tray| tray.set_visible(visible))?
+            .map_err(|e| {
+                Error::new(
+                    ErrorCode::PlatformError,
+                    format!("Failed to set visibility: {e}"),
+                )
+            })?;
+        state.attributes.lock().unwrap().visible = visible;
+        Ok(())
+    }
+
+    /// Like `setVisible`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_visible_async(&self, visible: bool) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_visible(visible))
+    }
+
+    /// Attaches `menu` as the context menu, replacing the current one, or removes the context
+    /// menu when `null`.
+    #[napi]
+    pub fn set_menu(&self, menu: Option<&Menu>) -> Result<(), ErrorCode> {
+        let state = self.state()?;
+        state.tray.with(|tray| -> Result<(), ErrorCode> {
+            let menu = menu.map(|menu| menu.inner.with(Clone::clone)).transpose()?;
+            tray.set_menu(menu.map(|menu| Box::new(menu) as Box<dyn ContextMenu>));
+            Ok(())
+        })??;
+        state.attributes.lock().unwrap().menu = menu.cloned();
+        Ok(())
+    }
+
+    /// Like `setMenu`, but returns a Promise.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn set_menu_async(&self, menu: Option<&Menu>) -> AsyncTask<UiTask<()>> {
+        let menu = menu.cloned();
+        let tray = self.clone();
+        ui_thread::run_async(move || tray.set_menu(menu.as_ref()))
+    }
+
+    /// The currently attached context menu, sharing its item registry with the `Menu` it was
+    /// attached from.
+    #[napi]
+    pub fn menu(&self) -> Result<Option<Menu>, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().menu.clone())
+    }
+
+    /// See `TrayIconBuilder.withMenuOnLeftClick`.
+    #[napi]
+    pub fn set_show_menu_on_left_click(&self, enable: bool) -> Result<(), ErrorCode> {
+        self.state()?
+            .tray
+            .with(|tray| tray.set_show_menu_on_left_click(enable))
+    }
+
+    /// See `TrayIconBuilder.withTempDirPath`. `null` restores the default directory.
+    #[napi]
+    pub fn set_temp_dir_path(&self, path: Option<String>) -> Result<(), ErrorCode> {
+        self.state()?
+            .tray
+            .with(|tray| tray.set_temp_dir_path(path.as_ref()))
+    }
+
+    /// See `TrayIconBuilder.withIconAsTemplate`.
+    #[napi]
+    pub fn set_icon_as_template(&self, is_template: bool) -> Result<(), ErrorCode> {
+        self.state()?
+            .tray
+            .with(|tray| tray.set_icon_as_template(is_template))
+    }
+
+    /// Position and size of the tray icon on screen, or `null` where the platform does not
+    /// report it (Linux).
+    #[napi]
+    pub fn rect(&self) -> Result<Option<Rect>, ErrorCode> {
+        Ok(self.state()?.tray.with(|tray| tray.rect())?.map(Rect::from))
+    }
+
+    #[napi]
+    pub fn is_visible(&self) -> Result<bool, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().visible)
+    }
+
+    #[napi]
+    pub fn tooltip(&self) -> Result<Option<String>, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().tooltip.clone())
+    }
+
+    #[napi]
+    pub fn title(&self) -> Result<Option<String>, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().title.clone())
+    }
+
+    /// The icon as last set, without any badge drawn on it.
+    #[napi]
+    pub fn icon(&self) -> Result<Option<Icon>, ErrorCode> {
+        Ok(self.state()?.attributes.lock().unwrap().icon.clone())
+    }
+
+    /// Removes the tray icon right away. Every other method throws afterwards; calling
+    /// `destroy()` again does nothing.
+    #[napi]
+    pub fn destroy(&self, env: Env) {
+        self.remove();
+        event_loop::update_keep_alive(&env);
+    }
+
+    /// Like `destroy`, but returns a Promise that resolves once the icon is gone.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn destroy_async(&self, env: Env) -> AsyncTask<UiTask<()>> {
+        let tray = self.clone();
+        let task = ui_thread::run_async(move || {
+            tray.remove();
+            Ok(())
+        });
+        // Outside threaded mode the icon is already gone at this point.
+        event_loop::update_keep_alive(&env);
+        task
+    }
+
+    fn remove(&self) {
+        let state = TRAYS.lock().unwrap().remove(&self.id);
+        // Dropped outside the registry lock since removing the icon runs on the UI thread.
+        drop(state);
+    }
+
+    #[napi]
+    pub fn is_destroyed(&self) -> bool {
+        !TRAYS.lock().unwrap().contains_key(&self.id)
+    }
+
+    /// The id events of this tray icon carry, as set with `TrayIconBuilder.withId`.
+    #[napi]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// Registers a listener that only receives the events of this tray icon.
+    #[napi]
+    pub fn on_event(
+        &self,
+        callback: Function<TrayIconEvent, ()>,
+    ) -> Result<EventSubscription, ErrorCode> {
+        let id = self.id.clone();
+        subscribe_callback(callback, move |event| event.id() == id)
+    }
+
+    /// Returns an async iterator over the events of this tray icon only.
+    #[napi]
+    pub fn events(&self, options: Option<WaitOptions>) -> TrayEventStream {
+        let id = self.id.clone();
+        TrayEventStream::subscribe(move |event| event.id() == id, options)
+    }
 }
 
 #[napi]
 #[derive(Clone)]
 pub struct TrayIconBuilder {
-    icon: Option<tray_icon::Icon>,
+    id: Option<String>,
+    icon: Option<Icon>,
     tooltip: Option<String>,
     title: Option<String>,
-    menu: Option<tray_icon::menu::Menu>,
+    menu: Option<Menu>,
+    menu_on_left_click: Option<bool>,
+    temp_dir_path: Option<String>,
+    icon_as_template: Option<bool>,
 }
 
 #[napi]
@@ -157,19 +786,34 @@ impl TrayIconBuilder {
     #[napi(constructor)]
     pub fn new() -> Self {
         Self {
+            id: None,
             icon: None,
             tooltip: None,
             title: None,
             menu: None,
+            menu_on_left_click: None,
+            temp_dir_path: None,
+            icon_as_template: None,
         }
     }
 
+    /// Sets the id reported in this tray icon's events. A unique id is generated otherwise.
     #[napi]
-    pub fn with_icon(&mut self, icon: &Icon) -> TrayIconBuilder {
-        self.icon = Some(icon.inner.clone());
+    pub fn with_id(&mut self, id: String) -> TrayIconBuilder {
+        self.id = Some(id);
         self.clone()
     }
 
+    /// Sets the icon, or picks the best fitting size out of an `IconSet`.
+    #[napi]
+    pub fn with_icon(
+        &mut self,
+        icon: Either<&Icon, &IconSet>,
+    ) -> Result<TrayIconBuilder, ErrorCode> {
+        self.icon = Some(resolve_icon(icon, IconTarget::Tray)?);
+        Ok(self.clone())
+    }
+
     #[napi]
     pub fn with_tooltip(&mut self, tooltip: String) -> TrayIconBuilder {
         self.tooltip = Some(tooltip);
@@ -184,30 +828,115 @@ impl TrayIconBuilder {
 
     #[napi]
     pub fn with_menu(&mut self, menu: &Menu) -> TrayIconBuilder {
-        self.menu = Some(menu.inner.clone());
+        self.menu = Some(menu.clone());
         self.clone()
     }
 
+    /// Whether a left click opens the menu (the default) or is only reported as a `Click` event.
+    /// Ignored on Linux, where the menu always opens.
     #[napi]
-    pub fn build(&self) -> Result<TrayIcon> {
-        let mut builder = RawTrayIconBuilder::new();
-        if let Some(icon) = &self.icon {
-            builder = builder.with_icon(icon.clone());
-        }
-        if let Some(tooltip) = &self.tooltip {
-            builder = builder.with_tooltip(tooltip);
-        }
-        if let Some(title) = &self.title {
-            builder = builder.with_title(title);
-        }
-        if let Some(menu) = &self.menu {
-            builder = builder.with_menu(Box::new(menu.clone()));
-        }
+    pub fn with_menu_on_left_click(&mut self, enable: bool) -> TrayIconBuilder {
+        self.menu_on_left_click = Some(enable);
+        self.clone()
+    }
 
-        let tray = builder
-            .build()
-            .map_err(|e| Error::from_reason(format!("Failed to build tray icon: {e}")))?;
-        Ok(TrayIcon(Some(tray)))
+    /// Directory the icon is written to as a PNG on Linux. Defaults to
+    /// `$XDG_RUNTIME_DIR/tray-icon` or `$TEMP/tray-icon`; ignored elsewhere.
+    #[napi]
+    pub fn with_temp_dir_path(&mut self, path: String) -> TrayIconBuilder {
+        self.temp_dir_path = Some(path);
+        self.clone()
+    }
+
+    /// Uses the icon as a template image that follows the menu bar appearance. macOS only.
+    #[napi]
+    pub fn with_icon_as_template(&mut self, is_template: bool) -> TrayIconBuilder {
+        self.icon_as_template = Some(is_template);
+        self.clone()
+    }
+
+    #[napi]
+    pub fn build(&self, env: Env) -> Result<TrayIcon, ErrorCode> {
+        let tray = self.create()?;
+        event_loop::update_keep_alive(&env);
+        Ok(tray)
+    }
+
+    /// Like `build`, but returns a Promise. In threaded mode the JS thread does not wait for
+    /// GTK to create the icon.
+    #[napi(ts_return_type = "Promise<TrayIcon>")]
+    pub fn build_async(&self, env: Env) -> AsyncTask<UiTask<TrayIcon>> {
+        let builder = self.clone();
+        let task = ui_thread::run_async(move || builder.create());
+        // Outside threaded mode the icon has been built at this point.
+        event_loop::update_keep_alive(&env);
+        task
+    }
+
+    fn create(&self) -> Result<TrayIcon, ErrorCode> {
+        install_tray_event_handler();
+        if let Some(id) = &self.id {
+            if TRAYS.lock().unwrap().contains_key(id) {
+                return Err(Error::new(
+                    ErrorCode::DuplicateId,
+                    format!("A tray icon with id {id} already exists"),
+                ));
+            }
+        }
+        let icon = self.icon.as_ref().map(Icon::tray_icon).transpose()?;
+        let tray = UiBound::try_new(|| {
+            let mut builder = RawTrayIconBuilder::new();
+            if let Some(id) = &self.id {
+                builder = builder.with_id(id.clone());
+            }
+            if let Some(icon) = icon {
+                builder = builder.with_icon(icon);
+            }
+            if let Some(tooltip) = &self.tooltip {
+                builder = builder.with_tooltip(tooltip);
+            }
+            if let Some(title) = &self.title {
+                builder = builder.with_title(title);
+            }
+            if let Some(menu) = &self.menu {
+                builder = builder.with_menu(Box::new(menu.inner.with(Clone::clone)?));
+            }
+            if let Some(enable) = self.menu_on_left_click {
+                builder = builder.with_menu_on_left_click(enable);
+            }
+            if let Some(path) = &self.temp_dir_path {
+                builder = builder.with_temp_dir_path(path);
+            }
+            if let Some(is_template) = self.icon_as_template {
+                builder = builder.with_icon_as_template(is_template);
+            }
+            builder.build().map_err(|e| {
+                Error::new(
+                    ErrorCode::PlatformError,
+                    format!("Failed to build tray icon: {e}"),
+                )
+            })
+        })?;
+        let id = tray.with(|tray| tray.id().0.clone())?;
+        TRAYS.lock().unwrap().insert(
+            id.clone(),
+            Arc::new(TrayState {
+                tray,
+                attributes: Mutex::new(TrayAttributes {
+                    icon: self.icon.clone(),
+                    tooltip: self.tooltip.clone(),
+                    title: self.title.clone(),
+                    visible: true,
+                    menu: self.menu.clone(),
+                    badge: None,
+                    animation: None,
+                    progress: None,
+                    progress_shown_at: None,
+                    progress_pending: false,
+                }),
+            }),
+        );
+        Ok(TrayIcon { id })
     }
 }
 
@@ -216,3 +945,47 @@ impl Default for TrayIconBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TrayAttributes;
+    use crate::animation::{Animation, AnimationOptions};
+    use crate::icon::Icon;
+    use crate::render::Progress;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn shows_the_progress_once_an_animation_ends() {
+        let frame = Icon::from_rgba_vec(vec![0; 4], 1, 1).unwrap();
+        let options = AnimationOptions {
+            fps: Some(1.0),
+            repeat: Some(false),
+        };
+        let animation = Animation::new(vec![frame.clone(); 2], Some(options), None).unwrap();
+        let mut attributes = TrayAttributes {
+            icon: Some(frame),
+            tooltip: None,
+            title: None,
+            visible: true,
+            menu: None,
+            badge: None,
+            animation: Some(animation),
+            // Set while the animation played.
+            progress: Some(Progress::new(0.5, None).unwrap()),
+            progress_shown_at: None,
+            progress_pending: false,
+        };
+
+        let start = Instant::now();
+        let due = attributes.due(start);
+        assert!(due.busy);
+        assert!(due.redraw.is_none());
+
+        let due = attributes.due(start + Duration::from_secs(2));
+        assert!(attributes.animation.is_none());
+        assert!(!due.busy);
+        let (icon, progress, _) = due.redraw.expect("the icon is redrawn with the progress");
+        assert!(icon.is_some());
+        assert!(progress.is_some());
+    }
+}
@@ -0,0 +1,336 @@
+use crate::error::ErrorCode;
+use napi::bindgen_prelude::*;
+use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+/// Thread that owns the platform UI toolkit when running in threaded mode.
+///
+/// Unset in the default mode, where everything happens on the JS thread that called
+/// `initialize()` and `update()` pumps the toolkit.
+static UI_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+pub(crate) fn is_threaded() -> bool {
+    UI_THREAD.get().is_some()
+}
+
+/// Runs `f` on the UI thread and returns its result, blocking until it has run.
+///
+/// Runs `f` inline when not in threaded mode or when already on the UI thread. Fails only if the
+/// UI thread is gone.
+pub(crate) fn run<R>(f: impl FnOnce() -> R) -> Result<R, ErrorCode> {
+    #[cfg(target_os = "linux")]
+    if let Some(thread) = UI_THREAD.get() {
+        if *thread != std::thread::current().id() {
+            return gtk_thread::dispatch(f);
+        }
+    }
+    Ok(f())
+}
+
+/// Like [`run`], but for the Promise-returning `*Async` methods: waits for the UI thread on a
+/// worker thread, so the JS thread can go on while it runs.
+///
+/// Outside threaded mode the objects belong to the JS thread, so `f` runs right away and the
+/// Promise only carries its result.
+pub(crate) fn run_async<T: ToNapiValue + TypeName + Send + 'static>(
+    f: impl FnOnce() -> Result<T, ErrorCode> + Send + 'static,
+) -> AsyncTask<UiTask<T>> {
+    AsyncTask::new(if is_threaded() {
+        UiTask::Pending(Some(Box::new(f)))
+    } else {
+        UiTask::Done(Some(f()))
+    })
+}
+
+/// An operation on tray or menu objects that resolves a Promise, see [`run_async`].
+pub enum UiTask<T> {
+    Pending(Option<Box<dyn FnOnce() -> Result<T, ErrorCode> + Send>>),
+    Done(Option<Result<T, ErrorCode>>),
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for UiTask<T> {
+    // Errors are carried through `resolve` so the rejection keeps its `code`.
+    type Output = Result<T, ErrorCode>;
+    type JsValue = T;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let output = match self {
+            Self::Pending(f) => f.take().map(|f| f()),
+            Self::Done(output) => output.take(),
+        };
+        Ok(output.expect("a task is computed once"))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        output.map_err(|e| JsError::from(e).into_unknown(env).into())
+    }
+}
+
+/// Wraps a native tray/menu object so that it is only ever created, used and dropped on the
+/// thread that owns it.
+///
+/// tray-icon and muda objects are not thread safe. Every access goes through [`UiBound::with`],
+/// which marshals it to the UI thread in threaded mode. Otherwise it runs inline, and is refused
+/// unless it happens on the thread the object was created on. Clones share the same object.
+pub(crate) struct UiBound<T>(Arc<Owned<T>>);
+
+struct Owned<T> {
+    value: ManuallyDrop<T>,
+    /// The thread `value` was created on, the UI thread in threaded mode.
+    owner: ThreadId,
+}
+
+// SAFETY: `value` is only touched from closures passed to `run` after checking that they execute
+// on `owner`, see `Owned::on_owner`.
+unsafe impl<T> Send for Owned<T> {}
+unsafe impl<T> Sync for Owned<T> {}
+
+impl<T> Owned<T> {
+    fn on_owner(&self) -> Result<(), ErrorCode> {
+        if std::thread::current().id() == self.owner {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorCode::InvalidState,
+                "Tray and menu objects can only be used on the thread that created them",
+            ))
+        }
+    }
+}
+
+impl<T> UiBound<T> {
+    pub(crate) fn new(f: impl FnOnce() -> T) -> Result<Self, ErrorCode> {
+        Self::try_new(|| Ok(f()))
+    }
+
+    pub(crate) fn try_new(f: impl FnOnce() -> Result<T, ErrorCode>) -> Result<Self, ErrorCode> {
+        run(|| {
+            f().map(|value| {
+                Self(Arc::new(Owned {
+                    value: ManuallyDrop::new(value),
+                    owner: std::thread::current().id(),
+                }))
+            })
+        })?
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, ErrorCode> {
+        run(|| {
+            self.0.on_owner()?;
+            Ok(f(&self.0.value))
+        })?
+    }
+}
+
+impl<T> Clone for UiBound<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for Owned<T> {
+    fn drop(&mut self) {
+        // Off the owner thread, or with the UI thread gone, the value is leaked instead.
+        let _ = run(|| {
+            if self.on_owner().is_ok() {
+                // SAFETY: the value is never used again after this.
+                unsafe { ManuallyDrop::drop(&mut self.value) }
+            }
+        });
+    }
+}
+
+/// Interval of the native timer that drives `tick`.
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Whether the tick timer is running. Only touched on the UI thread.
+static TICKING: AtomicBool = AtomicBool::new(false);
+
+/// Runs `tick` regularly on the UI thread until it returns `false`.
+///
+/// A native timer drives it: a GTK timer on Linux, `SetTimer` on Windows and an `NSTimer` on
+/// macOS. These fire whenever the platform event loop runs, so on the GTK thread in threaded
+/// mode, while a tray menu is open, and when `update()` pumps the loop.
+pub(crate) fn schedule_ticks(tick: fn() -> bool) {
+    let _ = run(move || {
+        if !TICKING.swap(true, Ordering::Relaxed) {
+            timer::start(tick);
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+mod timer {
+    use super::{TICKING, TICK_INTERVAL};
+    use std::sync::atomic::Ordering;
+
+    pub(super) fn start(tick: fn() -> bool) {
+        gtk::glib::timeout_add_local(TICK_INTERVAL, move || {
+            if tick() {
+                gtk::glib::ControlFlow::Continue
+            } else {
+                TICKING.store(false, Ordering::Relaxed);
+                gtk::glib::ControlFlow::Break
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod timer {
+    use super::{TICKING, TICK_INTERVAL};
+    use std::cell::Cell;
+    use std::sync::atomic::Ordering;
+    use windows_sys::Win32::Foundation::HWND;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+
+    thread_local! {
+        static TICK: Cell<Option<fn() -> bool>> = const { Cell::new(None) };
+    }
+
+    unsafe extern "system" fn on_timer(_: HWND, _: u32, id: usize, _: u32) {
+        if TICK.get().is_some_and(|tick| !tick()) {
+            // SAFETY: `id` is the thread timer that is firing.
+            unsafe { KillTimer(0, id) };
+            TICKING.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn start(tick: fn() -> bool) {
+        TICK.set(Some(tick));
+        // SAFETY: a timer without a window; `on_timer` is called on this thread when its message
+        // is dispatched.
+        let id = unsafe { SetTimer(0, 0, TICK_INTERVAL.as_millis() as u32, Some(on_timer)) };
+        if id == 0 {
+            TICKING.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod timer {
+    use super::{TICKING, TICK_INTERVAL};
+    use block2::RcBlock;
+    use objc2_foundation::{NSRunLoop, NSRunLoopCommonModes, NSTimer};
+    use std::ptr::NonNull;
+    use std::sync::atomic::Ordering;
+
+    pub(super) fn start(tick: fn() -> bool) {
+        let block = RcBlock::new(move |timer: NonNull<NSTimer>| {
+            if !tick() {
+                // SAFETY: the timer passes itself and is alive while it fires.
+                unsafe { timer.as_ref() }.invalidate();
+                TICKING.store(false, Ordering::Relaxed);
+            }
+        });
+        // SAFETY: the block only captures a function pointer, and the timer is added to this
+        // thread's run loop, so it only fires here. Common modes keep it firing while a tray
+        // menu is open.
+        unsafe {
+            let timer = NSTimer::timerWithTimeInterval_repeats_block(
+                TICK_INTERVAL.as_secs_f64(),
+                true,
+                &block,
+            );
+            NSRunLoop::currentRunLoop().addTimer_forMode(&timer, NSRunLoopCommonModes);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod timer {
+    /// No native timer here; `update()` does the ticking.
+    pub(super) fn start(_tick: fn() -> bool) {}
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use gtk_thread::spawn;
+
+#[cfg(target_os = "linux")]
+mod gtk_thread {
+    use super::UI_THREAD;
+    use crate::error::ErrorCode;
+    use napi::bindgen_prelude::*;
+    use std::sync::mpsc;
+
+    struct AssertSend<T>(T);
+
+    // SAFETY: only used to move values to and from the UI thread while the sender is blocked
+    // waiting for the result, see `dispatch`.
+    unsafe impl<T> Send for AssertSend<T> {}
+
+    impl<T> AssertSend<T> {
+        fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    /// Starts a dedicated thread that initializes GTK and runs `gtk::main()`.
+    pub(crate) fn spawn() -> Result<(), ErrorCode> {
+        if UI_THREAD.get().is_some() {
+            return Ok(());
+        }
+        if gtk::is_initialized() {
+            return Err(Error::new(
+                ErrorCode::InvalidState,
+                "GTK is already initialized on the JS thread",
+            ));
+        }
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("tray-icon-gtk".to_string())
+            .spawn(move || {
+                if gtk::init().is_err() {
+                    let _ = ready_tx.send(None);
+                    return;
+                }
+                let _ = ready_tx.send(Some(std::thread::current().id()));
+                gtk::main();
+            })
+            .map_err(|e| {
+                Error::new(
+                    ErrorCode::PlatformError,
+                    format!("Failed to start GTK thread: {e}"),
+                )
+            })?;
+
+        match ready_rx.recv() {
+            Ok(Some(thread)) => {
+                let _ = UI_THREAD.set(thread);
+                Ok(())
+            }
+            _ => Err(Error::new(
+                ErrorCode::PlatformError,
+                "Failed to initialize GTK",
+            )),
+        }
+    }
+
+    pub(super) fn dispatch<R>(f: impl FnOnce() -> R) -> Result<R, ErrorCode> {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let job: Box<dyn FnOnce() + '_> = Box::new(move || {
+            let result = f();
+            let _ = sender.send(AssertSend(result));
+        });
+        // SAFETY: glib only takes `'static` jobs, so the borrows of `job` are erased here. This
+        // is sound because we do not return before the job is gone: `recv` returns either once
+        // the job sent its result, after which the job only drops `sender`, which borrows
+        // nothing, or once `sender` was dropped because glib discarded the job without running
+        // it. If glib neither runs nor drops the job, `recv` never returns.
+        let job = unsafe {
+            std::mem::transmute::<Box<dyn FnOnce() + '_>, Box<dyn FnOnce() + 'static>>(job)
+        };
+        let job = AssertSend(job);
+        gtk::glib::MainContext::default().invoke(move || job.into_inner()());
+        receiver.recv().map(AssertSend::into_inner).map_err(|_| {
+            Error::new(
+                ErrorCode::PlatformError,
+                "The GTK thread stopped before running the call",
+            )
+        })
+    }
+}
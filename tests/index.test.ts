@@ -1,7 +1,262 @@
 import { expect, test, describe } from "bun:test";
+import { Icon, IconCache, IconSet } from "../index.js";
+import { mkdtempSync, readFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
 
 describe("NAPI Module Tests", () => {
     test("initialize", () => {
         expect(true).toBe(true);
     });
 });
+
+describe("Icon", () => {
+    const rgba = Buffer.from([
+        255, 0, 0, 255, 0, 255, 0, 255,
+        0, 0, 255, 255, 0, 0, 0, 0,
+    ]);
+
+    test("exposes its size and pixels", () => {
+        const icon = Icon.fromRgba(rgba, 2, 2);
+        expect(icon.width).toBe(2);
+        expect(icon.height).toBe(2);
+        expect(icon.toRgba()).toEqual(rgba);
+    });
+
+    test("round-trips through PNG", () => {
+        const png = Icon.fromRgba(rgba, 2, 2).toPng();
+        expect(Icon.fromBuffer(png, "png").toRgba()).toEqual(rgba);
+    });
+
+    test("validates sizes and limits", () => {
+        expect(() => Icon.fromRgba(rgba, 3, 2)).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+        expect(() => Icon.fromRgba(Buffer.alloc(0), 0, 0)).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+        const png = Icon.fromRgba(Buffer.alloc(64 * 64 * 4), 64, 64).toPng();
+        Icon.setLimits({ maxDimension: 32 });
+        try {
+            expect(() => Icon.fromBuffer(png)).toThrow(expect.objectContaining({ code: "LIMIT_EXCEEDED" }));
+        } finally {
+            Icon.setLimits({ maxDimension: 4096 });
+        }
+        expect(() => Icon.fromBuffer(Buffer.from("nope"))).toThrow(expect.objectContaining({ code: "UNSUPPORTED_FORMAT" }));
+    });
+
+    test("decodes base64 and data URLs", () => {
+        const base64 = Icon.fromRgba(rgba, 2, 2).toPng().toString("base64");
+        expect(Icon.fromBase64(base64).toRgba()).toEqual(rgba);
+        expect(Icon.fromBase64(`data:image/png;base64,${base64}`).toRgba()).toEqual(rgba);
+        expect(Icon.fromBase64(`data:;base64,${base64}`).toRgba()).toEqual(rgba);
+    });
+
+    test("rejects malformed base64 and data URLs", () => {
+        const base64 = Icon.fromRgba(rgba, 2, 2).toPng().toString("base64");
+        expect(() => Icon.fromBase64("not base64!")).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+        expect(() => Icon.fromBase64(`data:image/png,${base64}`)).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+        expect(() => Icon.fromBase64(`data:text/plain;base64,${base64}`)).toThrow(expect.objectContaining({ code: "UNSUPPORTED_FORMAT" }));
+        expect(() => Icon.fromBase64(`data:image/jpeg;base64,${base64}`)).toThrow(expect.objectContaining({ code: "IMAGE_ERROR" }));
+        expect(() => Icon.fromBase64(base64.slice(0, 40))).toThrow(expect.objectContaining({ code: "IMAGE_ERROR" }));
+    });
+
+    test("decodes asynchronously", async () => {
+        const png = Icon.fromRgba(rgba, 2, 2).toPng();
+        const icon = await Icon.fromBufferAsync(png);
+        expect(icon.toRgba()).toEqual(rgba);
+        await expect(Icon.fromBufferAsync(Buffer.from("nope"))).rejects.toMatchObject({
+            code: "UNSUPPORTED_FORMAT",
+        });
+    });
+});
+
+describe("Icon transforms", () => {
+    // Red, green / blue, transparent.
+    const icon = Icon.fromRgba(Buffer.from([
+        255, 0, 0, 255, 0, 255, 0, 255,
+        0, 0, 255, 255, 0, 0, 0, 0,
+    ]), 2, 2);
+    const pixels = (of: Icon) => [...of.toRgba()];
+    const solid = (size: number, color: number[]) =>
+        Icon.fromRgba(Buffer.from(Array.from({ length: size * size }, () => color).flat()), size, size);
+
+    test("resize", () => {
+        const resized = solid(4, [10, 20, 30, 255]).resize(2, 3, "nearest");
+        expect([resized.width, resized.height]).toEqual([2, 3]);
+        expect(pixels(resized)).toEqual(Array(6).fill([10, 20, 30, 255]).flat());
+        expect(() => icon.resize(0, 2)).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+    });
+
+    test("grayscale", () => {
+        expect(pixels(icon.grayscale())).toEqual([
+            76, 76, 76, 255, 150, 150, 150, 255,
+            29, 29, 29, 255, 0, 0, 0, 0,
+        ]);
+    });
+
+    test("tint", () => {
+        expect(pixels(icon.tint("#00ff00"))).toEqual([
+            0, 255, 0, 255, 0, 255, 0, 255,
+            0, 255, 0, 255, 0, 255, 0, 0,
+        ]);
+        expect(pixels(icon.tint("#00ff00", 0.5)).slice(0, 4)).toEqual([128, 128, 0, 255]);
+        expect(pixels(icon.tint("#00ff0000"))).toEqual(pixels(icon));
+    });
+
+    test("invert", () => {
+        expect(pixels(icon.invert())).toEqual([
+            0, 255, 255, 255, 255, 0, 255, 255,
+            255, 255, 0, 255, 255, 255, 255, 0,
+        ]);
+    });
+
+    test("opacity", () => {
+        expect(pixels(icon.opacity(0.5))).toEqual([
+            255, 0, 0, 128, 0, 255, 0, 128,
+            0, 0, 255, 128, 0, 0, 0, 0,
+        ]);
+        expect(() => icon.opacity(1.5)).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+    });
+
+    test("pad", () => {
+        const padded = icon.pad(1);
+        expect([padded.width, padded.height]).toEqual([4, 4]);
+        const clear = [0, 0, 0, 0];
+        expect(pixels(padded)).toEqual([
+            ...clear, ...clear, ...clear, ...clear,
+            ...clear, 255, 0, 0, 255, 0, 255, 0, 255, ...clear,
+            ...clear, 0, 0, 255, 255, 0, 0, 0, 0, ...clear,
+            ...clear, ...clear, ...clear, ...clear,
+        ]);
+    });
+
+    test("crop", () => {
+        const cropped = icon.crop({ x: 1, y: 0, width: 1, height: 2 });
+        expect([cropped.width, cropped.height]).toEqual([1, 2]);
+        expect(pixels(cropped)).toEqual([0, 255, 0, 255, 0, 0, 0, 0]);
+        expect(() => icon.crop({ x: 1, y: 1, width: 2, height: 1 })).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+    });
+});
+
+describe("IconSet", () => {
+    const square = (size: number) => Icon.fromRgba(Buffer.alloc(size * size * 4), size, size);
+
+    test("selects the smallest icon that is large enough", () => {
+        const set = new IconSet();
+        set.add(square(64));
+        set.add(square(16));
+        set.add(square(32));
+        set.add(square(32));
+        expect(set.sizes()).toEqual([16, 32, 64]);
+        expect(set.select(16)?.width).toBe(16);
+        expect(set.select(20)?.width).toBe(32);
+        expect(set.select(48)?.width).toBe(64);
+        expect(set.select(256)?.width).toBe(64);
+        expect(new IconSet().select(16)).toBeNull();
+    });
+});
+
+describe("Badges", () => {
+    const base = Icon.fromRgba(Buffer.alloc(32 * 32 * 4), 32, 32);
+    const badge = (options: Parameters<Icon["withBadge"]>[0]) => base.withBadge(options).toRgba();
+
+    test("caps counts at max", () => {
+        expect(badge({ count: 150 })).toEqual(badge({ text: "99+" }));
+        expect(badge({ count: 99 })).toEqual(badge({ text: "99" }));
+        expect(badge({ count: 99 })).not.toEqual(badge({ text: "99+" }));
+        expect(badge({ count: 12, max: 9 })).toEqual(badge({ text: "9+" }));
+    });
+
+    test("draws nothing for a zero count", () => {
+        expect(badge({ count: 0 })).toEqual(base.toRgba());
+        expect(badge({ count: 1 })).not.toEqual(base.toRgba());
+    });
+});
+
+describe("Text icons", () => {
+    test("are square and sized as requested", () => {
+        const icon = Icon.fromText("A");
+        expect([icon.width, icon.height]).toEqual([32, 32]);
+        const small = Icon.fromText("A", { size: 16 });
+        expect([small.width, small.height]).toEqual([16, 16]);
+        expect(() => Icon.fromText("A", { size: 0 })).toThrow(expect.objectContaining({ code: "INVALID_ARGUMENT" }));
+    });
+
+    test("shrink long text to fit", () => {
+        const size = 64;
+        const rgba = Icon.fromText("WWWWWW", { size }).toRgba();
+        const alpha = (x: number, y: number) => rgba[(y * size + x) * 4 + 3];
+        const edge: number[] = [];
+        for (let i = 0; i < size; i++) {
+            edge.push(alpha(i, 0), alpha(i, 1), alpha(i, size - 1), alpha(0, i), alpha(1, i), alpha(size - 1, i));
+        }
+        expect(edge.every((value) => value === 0)).toBe(true);
+        expect(rgba.some((value, i) => i % 4 === 3 && value > 0)).toBe(true);
+    });
+
+    test("fill the background", () => {
+        const icon = Icon.fromText("", { size: 4, background: "#102030" });
+        expect([...icon.toRgba()]).toEqual(Array(16).fill([16, 32, 48, 255]).flat());
+    });
+});
+
+describe("IconCache", () => {
+    test("decodes a file once", () => {
+        const path = join(mkdtempSync(join(tmpdir(), "tray-icon-")), "icon.png");
+        Icon.fromRgba(Buffer.alloc(16 * 16 * 4, 255), 16, 16).save(path);
+        IconCache.clear();
+        IconCache.get(path);
+        Icon.fromPath(path, { cache: true });
+        expect(IconCache.stats()).toEqual({ entries: 1, bytes: 16 * 16 * 4 });
+        expect(IconCache.evict(path)).toBe(true);
+        expect(IconCache.stats().entries).toBe(0);
+    });
+
+    test("counts shared pixels once", () => {
+        const dir = mkdtempSync(join(tmpdir(), "tray-icon-"));
+        const icon = Icon.fromRgba(Buffer.alloc(16 * 16 * 4, 255), 16, 16);
+        icon.save(join(dir, "a.png"));
+        icon.save(join(dir, "b.png"));
+        IconCache.clear();
+        IconCache.get(join(dir, "a.png"));
+        IconCache.get(join(dir, "b.png"));
+        expect(IconCache.stats()).toEqual({ entries: 2, bytes: 16 * 16 * 4 });
+        IconCache.evict(join(dir, "a.png"));
+        expect(IconCache.stats()).toEqual({ entries: 1, bytes: 16 * 16 * 4 });
+    });
+
+    test("checks files again only after refresh", () => {
+        const path = join(mkdtempSync(join(tmpdir(), "tray-icon-")), "icon.png");
+        Icon.fromRgba(Buffer.alloc(16 * 16 * 4, 255), 16, 16).save(path);
+        IconCache.clear();
+        expect(IconCache.get(path).width).toBe(16);
+        Icon.fromRgba(Buffer.alloc(8 * 8 * 4, 255), 8, 8).save(path);
+        expect(IconCache.get(path).width).toBe(16);
+        IconCache.refresh(path);
+        expect(IconCache.get(path).width).toBe(8);
+    });
+});
+
+describe("TrayIconEvent", () => {
+    test("uses kebab-case type strings", () => {
+        const typings = readFileSync(new URL("../index.d.ts", import.meta.url), "utf8");
+        const union = typings.split("export type TrayIconEvent =")[1].split("\n\n")[0];
+        const types = [...union.matchAll(/\| \{ type: '([^']+)'/g)].map((match) => match[1]);
+        expect(types).toEqual(["click", "double-click", "enter", "move", "leave", "unknown"]);
+    });
+});
+
+describe("Promise variants", () => {
+    test("return promises", () => {
+        const typings = readFileSync(new URL("../index.d.ts", import.meta.url), "utf8");
+        const methods = [
+            "buildAsync",
+            "setIconAsync",
+            "setTooltipAsync",
+            "destroyAsync",
+            "appendMenuItemAsync",
+            "toggleCheckAsync",
+            "setCheckedAsync",
+        ];
+        for (const method of methods) {
+            expect(typings).toMatch(new RegExp(`\\b${method}\\([^)]*\\): Promise<`));
+        }
+    });
+});